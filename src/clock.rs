@@ -0,0 +1,17 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+static ANCHOR: OnceLock<Instant> = OnceLock::new();
+
+/// Microseconds elapsed since the process-wide monotonic anchor.
+///
+/// All timestamps recorded by the benchmark (send start, send ack, confirmation) come from this
+/// clock so they can be subtracted from each other regardless of which thread recorded them.
+pub fn now_us() -> u64 {
+    ANCHOR.get_or_init(Instant::now).elapsed().as_micros() as u64
+}
+
+/// The instant a timestamp returned by `now_us` refers to.
+pub fn instant_at(timestamp_us: u64) -> Instant {
+    *ANCHOR.get_or_init(Instant::now) + Duration::from_micros(timestamp_us)
+}
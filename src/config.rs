@@ -1,35 +1,1040 @@
-use anyhow::Result;
-use clap::Parser;
+use crate::transaction::BUILD_CHUNK_SIZE;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
+use solana_client::client_error::reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::str::FromStr;
+
+/// What a node is used for in a run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeRole {
+    /// Transactions are sent to it over HTTP and it is watched over WebSocket.
+    #[default]
+    Both,
+    /// Transactions are only sent to it, e.g. a staked-connection endpoint without WebSocket.
+    Send,
+    /// It is only watched over WebSocket.
+    Watch,
+}
+
+impl NodeRole {
+    pub fn sends(&self) -> bool {
+        matches!(self, NodeRole::Both | NodeRole::Send)
+    }
+
+    pub fn watches(&self) -> bool {
+        matches!(self, NodeRole::Both | NodeRole::Watch)
+    }
+}
+
+impl std::fmt::Display for NodeRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeRole::Both => write!(f, "both"),
+            NodeRole::Send => write!(f, "send"),
+            NodeRole::Watch => write!(f, "watch"),
+        }
+    }
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RpcNode {
     pub name: String,
-    pub http_url: String,
-    pub ws_url: String,
+    /// Required unless the node only watches.
+    #[serde(default)]
+    pub http_url: Option<String>,
+    /// Required unless the node only sends.
+    #[serde(default)]
+    pub ws_url: Option<String>,
+    #[serde(default)]
+    pub role: NodeRole,
+    /// Overrides `send_concurrency` for this node.
+    #[serde(default)]
+    pub send_concurrency: Option<usize>,
+    /// Overrides the fields it sets of `send_options` for this node.
+    #[serde(default)]
+    pub send_options: SendOptions,
+    /// Sent with every HTTP request and the WebSocket handshake, e.g. `Authorization`. `${VAR}`
+    /// in a value is replaced with the environment variable `VAR`.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// `headers`, resolved and validated once by `BenchmarkConfig::from_file` so every request
+    /// and reconnect of the run sends the same values.
+    #[serde(skip)]
+    pub resolved_headers: HeaderMap,
+}
+
+impl RpcNode {
+    /// `headers` with variables substituted from `env`, as sensitive header values.
+    fn resolve_headers(&self, env: impl Fn(&str) -> Option<String>) -> Result<HeaderMap> {
+        let mut header_map = HeaderMap::with_capacity(self.headers.len());
+        for (name, value) in &self.headers {
+            let context = || format!("Invalid header {} of node {}", name, self.name);
+            let mut value =
+                HeaderValue::from_str(&expand_env_vars(value, &env).with_context(context)?)
+                    .with_context(context)?;
+            // Keep credentials out of debug output
+            value.set_sensitive(true);
+            header_map.insert(
+                HeaderName::from_bytes(name.as_bytes()).with_context(context)?,
+                value,
+            );
+        }
+        Ok(header_map)
+    }
+}
+
+/// Replace every `${VAR}` in `value` with the variable `VAR` looked up in `env`.
+fn expand_env_vars(value: &str, env: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let reference = &rest[start + 2..];
+        let end = reference
+            .find('}')
+            .ok_or_else(|| anyhow::anyhow!("Unterminated `${{`"))?;
+        let name = &reference[..end];
+        let resolved = env(name)
+            .ok_or_else(|| anyhow::anyhow!("Cannot read environment variable {}", name))?;
+        expanded.push_str(&resolved);
+        rest = &reference[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Wire encoding of transactions passed to `sendTransaction`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SendEncoding {
+    Base58,
+    Base64,
+}
+
+impl std::fmt::Display for SendEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SendEncoding::Base58 => write!(f, "base58"),
+            SendEncoding::Base64 => write!(f, "base64"),
+        }
+    }
+}
+
+/// `sendTransaction` options. Unset fields keep the RPC client's defaults.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SendOptions {
+    pub skip_preflight: Option<bool>,
+    /// Commitment the preflight simulation runs at (the client's `confirmed` by default).
+    pub preflight_commitment: Option<Commitment>,
+    /// Times the node rebroadcasts the transaction itself; left to the node when unset.
+    pub max_retries: Option<usize>,
+    pub encoding: Option<SendEncoding>,
+    pub min_context_slot: Option<u64>,
+}
+
+impl SendOptions {
+    /// These options with every field `overrides` sets replaced.
+    pub fn with_overrides(&self, overrides: &SendOptions) -> SendOptions {
+        SendOptions {
+            skip_preflight: overrides.skip_preflight.or(self.skip_preflight),
+            preflight_commitment: overrides.preflight_commitment.or(self.preflight_commitment),
+            max_retries: overrides.max_retries.or(self.max_retries),
+            encoding: overrides.encoding.or(self.encoding),
+            min_context_slot: overrides.min_context_slot.or(self.min_context_slot),
+        }
+    }
+}
+
+/// Commitment level at which a signature notification is delivered.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Commitment {
+    #[default]
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl Commitment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Commitment::Processed => "processed",
+            Commitment::Confirmed => "confirmed",
+            Commitment::Finalized => "finalized",
+        }
+    }
+}
+
+impl std::fmt::Display for Commitment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Roughly how long a transaction signed against a fresh blockhash can still land: 150 slots
+/// of about 400 ms.
+const BLOCKHASH_LIFETIME_SECS: f64 = 60.0;
+
+/// `sendTransaction` round trip assumed when estimating how long unpaced sending takes.
+const ASSUMED_SEND_ROUND_TRIP_SECS: f64 = 0.05;
+
+fn default_commitments() -> Vec<Commitment> {
+    vec![Commitment::Processed]
+}
+
+fn default_signature_timeout_ms() -> u64 {
+    90_000
+}
+
+/// Exponential backoff policy for re-establishing a lost WebSocket connection.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectConfig {
+    /// Consecutive reconnect attempts before giving up on a node; 0 disables reconnecting.
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 10_000,
+        }
+    }
+}
+
+/// When the transaction builder fetches a new blockhash. Without either limit a single
+/// blockhash is used for the whole batch.
+///
+/// Transactions are signed before sending starts, so this only keeps a long build fresh: it is
+/// checked between chunks of `transaction::BUILD_CHUNK_SIZE` transactions and cannot extend
+/// the life of a transaction waiting to be sent.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlockhashRefresh {
+    /// Refetch once the blockhash is this many milliseconds old.
+    pub interval_ms: Option<u64>,
+    /// Refetch once the cluster has advanced this many slots since the blockhash was fetched.
+    pub max_slot_age: Option<u64>,
+}
+
+impl BlockhashRefresh {
+    fn is_set(&self) -> bool {
+        self.interval_ms.is_some() || self.max_slot_age.is_some()
+    }
+}
+
+/// Account passed to a `program_invoke` template instruction.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AccountMetaConfig {
+    pub pubkey: String,
+    #[serde(default)]
+    pub is_signer: bool,
+    #[serde(default)]
+    pub is_writable: bool,
+}
+
+/// What each benchmark transaction does.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TemplateConfig {
+    /// Transfer `amount_lamports` (plus the transaction index) to `recipient`.
+    #[default]
+    SolTransfer,
+    /// A memo with `message` and the transaction index.
+    Memo {
+        #[serde(default = "default_memo_message")]
+        message: String,
+    },
+    /// Transfer `amount` (plus the transaction index) base units of `mint` from the payer's
+    /// associated token account to `recipient`'s, which must already exist.
+    SplTransfer {
+        mint: String,
+        decimals: u8,
+        amount: u64,
+    },
+    /// Invoke `program_id` with the given accounts and base64 `data`. The payer is the only
+    /// signer available.
+    ProgramInvoke {
+        program_id: String,
+        #[serde(default)]
+        accounts: Vec<AccountMetaConfig>,
+        #[serde(default)]
+        data: String,
+        /// Append a memo with the transaction index, so identical instructions still get
+        /// distinct signatures at the cost of an extra instruction.
+        #[serde(default)]
+        index_memo: bool,
+    },
+}
+
+impl TemplateConfig {
+    /// Whether transactions carry a memo with their index on top of the configured
+    /// instruction.
+    pub fn adds_index_memo(&self) -> bool {
+        matches!(
+            self,
+            TemplateConfig::ProgramInvoke {
+                index_memo: true,
+                ..
+            }
+        )
+    }
+}
+
+fn default_send_concurrency() -> usize {
+    1
+}
+
+fn default_memo_message() -> String {
+    "usopp".to_string()
+}
+
+/// Message format of the benchmark transactions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionVersion {
+    #[default]
+    Legacy,
+    V0,
+}
+
+/// Sign transactions against durable nonce accounts instead of a recent blockhash.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DurableNonceConfig {
+    /// Nonce accounts with the payer as authority, one per transaction. Create them with the
+    /// `create-nonce-accounts` subcommand.
+    pub accounts: Vec<String>,
+}
+
+/// Arrival pattern of paced sends. The same schedule applies to every node.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "pattern", rename_all = "snake_case")]
+pub enum PacingConfig {
+    /// Evenly spaced sends at `target_tps`.
+    Constant { target_tps: f64 },
+    /// Exponentially distributed gaps averaging `target_tps`.
+    Poisson { target_tps: f64 },
+    /// `size` sends at once every `interval_ms`.
+    Burst { size: usize, interval_ms: u64 },
+}
+
+impl PacingConfig {
+    /// Average sends per second per node the schedule aims for.
+    pub fn target_tps(&self) -> f64 {
+        match self {
+            PacingConfig::Constant { target_tps } | PacingConfig::Poisson { target_tps } => {
+                *target_tps
+            }
+            PacingConfig::Burst { size, interval_ms } => {
+                *size as f64 * 1_000.0 / *interval_ms as f64
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for PacingConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PacingConfig::Constant { target_tps } => write!(f, "constant ({} tx/s)", target_tps),
+            PacingConfig::Poisson { target_tps } => write!(f, "poisson ({} tx/s)", target_tps),
+            PacingConfig::Burst { size, interval_ms } => {
+                write!(f, "burst ({} every {} ms)", size, interval_ms)
+            }
+        }
+    }
+}
+
+/// Coordination of each transaction's sends across nodes, so no node systematically receives
+/// transactions first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FairnessConfig {
+    /// Send a transaction only once every node is ready to send it.
+    pub barrier: bool,
+    /// Start sending each transaction to the nodes in a fresh random order.
+    pub randomize_order: bool,
+}
+
+impl std::fmt::Display for FairnessConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.barrier, self.randomize_order) {
+            (false, false) => write!(f, "none"),
+            (true, false) => write!(f, "barrier"),
+            (false, true) => write!(f, "random order"),
+            (true, true) => write!(f, "barrier, random order"),
+        }
+    }
+}
+
+/// Client-side rebroadcasting of every transaction to every node until it lands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RebroadcastConfig {
+    #[serde(default = "default_rebroadcast_interval_ms")]
+    pub interval_ms: u64,
+    /// Sends per transaction and node, including the first.
+    pub max_attempts: usize,
+    /// Stop once any node's WebSocket monitor has been notified of the transaction.
+    #[serde(default = "default_stop_on_processed")]
+    pub stop_on_processed: bool,
+}
+
+fn default_rebroadcast_interval_ms() -> u64 {
+    400
+}
+
+fn default_stop_on_processed() -> bool {
+    true
+}
+
+impl std::fmt::Display for RebroadcastConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "every {} ms, at most {} sends per node",
+            self.interval_ms, self.max_attempts
+        )?;
+        if self.stop_on_processed {
+            write!(f, ", until notified")?;
+        }
+        Ok(())
+    }
+}
+
+/// Send each transaction through a single node instead of all of them, while every node still
+/// watches for it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "sender", rename_all = "snake_case")]
+pub enum PropagationConfig {
+    /// Spread transactions across the nodes, round-robin.
+    RoundRobin,
+    /// Send every transaction through the named node.
+    Fixed { node: String },
+}
+
+impl std::fmt::Display for PropagationConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropagationConfig::RoundRobin => write!(f, "round-robin sender"),
+            PropagationConfig::Fixed { node } => write!(f, "fixed sender {}", node),
+        }
+    }
+}
+
+/// How the report charges a node for signatures it never confirmed.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum MissingPenalty {
+    /// Each missed signature adds a fixed penalty to the node's score.
+    Fixed { penalty_us: u64 },
+    /// Each missed signature adds the largest Δ observed across all nodes in the run.
+    #[default]
+    MaxObserved,
+    /// Nodes that missed any signature are left out of the ranking.
+    Exclude,
+}
+
+impl std::fmt::Display for MissingPenalty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MissingPenalty::Fixed { penalty_us } => write!(f, "fixed ({} μs)", penalty_us),
+            MissingPenalty::MaxObserved => write!(f, "max_observed"),
+            MissingPenalty::Exclude => write!(f, "exclude"),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BenchmarkConfig {
+    /// First payer; also the durable nonce authority.
     pub keypair_path: PathBuf,
+    /// Further payer keypairs. Transactions are assigned to payers round-robin.
+    #[serde(default)]
+    pub payer_keypair_paths: Vec<PathBuf>,
+    /// Directory whose `*.json` keypair files are added as payers, in file name order.
+    #[serde(default)]
+    pub payer_keypair_dir: Option<PathBuf>,
     pub recipient: String,
+    /// Recipients assigned to transactions round-robin; `recipient` is used when empty.
+    #[serde(default)]
+    pub recipients: Vec<String>,
     pub amount_lamports: u64,
     pub num_transactions: usize,
     pub rpc_nodes: Vec<RpcNode>,
+    /// Maximum `sendTransaction` calls in flight per node.
+    #[serde(default = "default_send_concurrency")]
+    pub send_concurrency: usize,
+    /// Release sends on a schedule instead of as fast as each node accepts them.
+    #[serde(default)]
+    pub pacing: Option<PacingConfig>,
+    #[serde(default)]
+    pub fairness: FairnessConfig,
+    /// `sendTransaction` options for every node; see `RpcNode::send_options`.
+    #[serde(default)]
+    pub send_options: SendOptions,
+    /// Resend transactions until they land; each is sent once per node when unset.
+    #[serde(default)]
+    pub rebroadcast: Option<RebroadcastConfig>,
+    /// Send each transaction to a single node; it is sent to every node when unset.
+    #[serde(default)]
+    pub propagation: Option<PropagationConfig>,
+    #[serde(default)]
+    pub missing_penalty: MissingPenalty,
+    /// Commitment levels to track for every signature on every node. The first entry is the
+    /// primary commitment used for ranking.
+    #[serde(default = "default_commitments")]
+    pub commitments: Vec<Commitment>,
+    /// Give up on a signature this many milliseconds after it was first sent.
+    #[serde(default = "default_signature_timeout_ms")]
+    pub signature_timeout_ms: u64,
+    /// Give up on all still-pending signatures this many milliseconds after monitoring started.
+    #[serde(default)]
+    pub monitor_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+    #[serde(default)]
+    pub blockhash_refresh: BlockhashRefresh,
+    #[serde(default)]
+    pub template: TemplateConfig,
+    #[serde(default)]
+    pub transaction_version: TransactionVersion,
+    /// Address lookup tables to compile v0 messages against.
+    #[serde(default)]
+    pub address_lookup_tables: Vec<String>,
+    /// Use durable nonces instead of a recent blockhash; excludes `blockhash_refresh`.
+    #[serde(default)]
+    pub durable_nonce: Option<DurableNonceConfig>,
+    /// Adds a `SetComputeUnitLimit` instruction to every transaction.
+    #[serde(default)]
+    pub compute_unit_limit: Option<u32>,
+    /// Priority fee in micro-lamports per compute unit, added as a `SetComputeUnitPrice`
+    /// instruction to every transaction.
+    #[serde(default)]
+    pub compute_unit_price: Option<u64>,
+    /// Compute unit prices to spread transactions across, round-robin. Overrides
+    /// `compute_unit_price`.
+    #[serde(default)]
+    pub fee_sweep: Option<Vec<u64>>,
 }
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 pub struct CliArgs {
-    /// Path to config file (required)
-    #[arg(short, long)]
-    pub config: PathBuf,
+    /// Path to config file (required unless a subcommand is given)
+    #[arg(short, long, required = true)]
+    pub config: Option<PathBuf>,
+
+    /// Path of the JSONL event log written during the run
+    /// [default: usopp-events-<unix timestamp>.jsonl]
+    #[arg(long)]
+    pub event_log: Option<PathBuf>,
+
+    /// Format of the generated report
+    #[arg(long, value_enum, default_value_t = OutputFormat::Markdown, global = true)]
+    pub output_format: OutputFormat,
+
+    /// Write the report to this file instead of the log (markdown) or stdout (json); required
+    /// for csv, whose per-node summary is written next to it with a `.summary.csv` extension.
+    #[arg(short, long, global = true)]
+    pub output: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Rebuild the report from a previously recorded event log without touching the network
+    Report {
+        /// Path to the JSONL event log of a previous run
+        #[arg(long)]
+        from: PathBuf,
+    },
+    /// Create and fund durable nonce accounts from the configured payer, with the payer as
+    /// nonce authority, and print them as a `durable_nonce` config entry
+    CreateNonceAccounts {
+        /// Number of nonce accounts to create
+        #[arg(long)]
+        count: usize,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Markdown,
+    Json,
+    Csv,
 }
 
 impl BenchmarkConfig {
     pub fn from_file(path: &PathBuf) -> Result<Self> {
         let contents = std::fs::read_to_string(path)?;
-        Ok(serde_json::from_str(&contents)?)
+        let mut config: Self = serde_json::from_str(&contents)?;
+
+        if config.commitments.is_empty() {
+            anyhow::bail!("`commitments` must contain at least one commitment level");
+        }
+        let mut seen = std::collections::HashSet::new();
+        config
+            .commitments
+            .retain(|commitment| seen.insert(*commitment));
+
+        if !config.address_lookup_tables.is_empty()
+            && config.transaction_version != TransactionVersion::V0
+        {
+            anyhow::bail!("`address_lookup_tables` require `transaction_version` \"v0\"");
+        }
+
+        if config
+            .fee_sweep
+            .as_ref()
+            .is_some_and(|prices| prices.is_empty())
+        {
+            anyhow::bail!("`fee_sweep` must contain at least one compute unit price");
+        }
+
+        match config.pacing {
+            Some(PacingConfig::Constant { target_tps } | PacingConfig::Poisson { target_tps })
+                if !(target_tps.is_finite() && target_tps > 0.0) =>
+            {
+                anyhow::bail!("`pacing.target_tps` must be a positive number");
+            }
+            Some(PacingConfig::Burst { size, interval_ms }) if size == 0 || interval_ms == 0 => {
+                anyhow::bail!("`pacing` bursts need a positive `size` and `interval_ms`");
+            }
+            _ => {}
+        }
+
+        if config.blockhash_refresh.is_set() {
+            if config.durable_nonce.is_some() {
+                anyhow::bail!("`blockhash_refresh` has no effect with `durable_nonce`");
+            }
+            if config.num_transactions <= BUILD_CHUNK_SIZE {
+                tracing::warn!(
+                    "`blockhash_refresh` only applies while building, between chunks of {} \
+                     transactions, so it has no effect on {} transactions",
+                    BUILD_CHUNK_SIZE,
+                    config.num_transactions
+                );
+            }
+        }
+        if config.send_concurrency == 0 {
+            anyhow::bail!("`send_concurrency` must be positive");
+        }
+        if let Some(node) = config
+            .rpc_nodes
+            .iter()
+            .find(|node| node.send_concurrency == Some(0))
+        {
+            anyhow::bail!("Node {} has a `send_concurrency` of 0", node.name);
+        }
+
+        for node in &mut config.rpc_nodes {
+            node.resolved_headers = node.resolve_headers(|name| std::env::var(name).ok())?;
+        }
+        for node in &config.rpc_nodes {
+            if node.role.sends() && node.http_url.is_none() {
+                anyhow::bail!(
+                    "Node {} sends transactions but has no `http_url`",
+                    node.name
+                );
+            }
+            if node.role.watches() && node.ws_url.is_none() {
+                anyhow::bail!("Node {} is watched but has no `ws_url`", node.name);
+            }
+        }
+        if config.send_nodes().next().is_none() {
+            anyhow::bail!("At least one of `rpc_nodes` must send transactions");
+        }
+        if config.watch_nodes().next().is_none() {
+            anyhow::bail!("At least one of `rpc_nodes` must be watched");
+        }
+
+        // Sends beyond a node's concurrency wait for a response, which would spread each burst
+        // out at the node's round-trip time. Rebroadcasts need a permit of their own on top of
+        // the ones they leave to first sends.
+        if let Some((node, _)) = config
+            .send_nodes()
+            .find(|(node, _)| config.send_concurrency_for(node) < config.min_send_concurrency(node))
+        {
+            let burst = match config.pacing {
+                Some(PacingConfig::Burst { size, .. }) => Some(format!(
+                    "for `pacing` bursts of {}, which send {} transactions to it at once",
+                    size,
+                    config.burst_share(node)
+                )),
+                _ => None,
+            };
+            let rebroadcast = config
+                .rebroadcast
+                .map(|_| "to rebroadcast without holding up first sends".to_string());
+            anyhow::bail!(
+                "Node {} needs a `send_concurrency` of at least {} {}, but has {}",
+                node.name,
+                config.min_send_concurrency(node),
+                burst
+                    .into_iter()
+                    .chain(rebroadcast)
+                    .collect::<Vec<_>>()
+                    .join(" and "),
+                config.send_concurrency_for(node)
+            );
+        }
+
+        if let Some(PropagationConfig::Fixed { node }) = &config.propagation {
+            if !config
+                .send_nodes()
+                .any(|(rpc_node, _)| &rpc_node.name == node)
+            {
+                anyhow::bail!(
+                    "`propagation` sender {} is not one of the sending `rpc_nodes`",
+                    node
+                );
+            }
+        }
+
+        if config.durable_nonce.is_none() {
+            let send_span_secs = config.estimated_send_span_secs();
+            if send_span_secs > BLOCKHASH_LIFETIME_SECS {
+                tracing::warn!(
+                    "Sending will take about {:.0} s, but transactions are signed up front and \
+                     their blockhash expires after about {:.0} s; late sends will be rejected. \
+                     Use `durable_nonce` for long runs.",
+                    send_span_secs,
+                    BLOCKHASH_LIFETIME_SECS
+                );
+            }
+        }
+
+        if config.rebroadcast.is_some_and(|rebroadcast| {
+            rebroadcast.max_attempts == 0 || rebroadcast.interval_ms == 0
+        }) {
+            anyhow::bail!("`rebroadcast` needs a positive `max_attempts` and `interval_ms`");
+        }
+
+        Ok(config)
+    }
+
+    /// Nodes transactions are sent to, with their HTTP URL.
+    pub fn send_nodes(&self) -> impl Iterator<Item = (&RpcNode, &str)> {
+        self.rpc_nodes.iter().filter_map(|node| {
+            node.role
+                .sends()
+                .then_some(node.http_url.as_deref())
+                .flatten()
+                .map(|url| (node, url))
+        })
+    }
+
+    /// Nodes watched for notifications, with their WebSocket URL.
+    pub fn watch_nodes(&self) -> impl Iterator<Item = (&RpcNode, &str)> {
+        self.rpc_nodes.iter().filter_map(|node| {
+            node.role
+                .watches()
+                .then_some(node.ws_url.as_deref())
+                .flatten()
+                .map(|url| (node, url))
+        })
+    }
+
+    /// Node used to build transactions, check balances and create nonce accounts, with its HTTP
+    /// URL: the first watched node with one, or else the first send-only node, since those are
+    /// often `sendTransaction`-only endpoints.
+    pub fn http_node(&self) -> Result<(&RpcNode, &str)> {
+        let with_http = || {
+            self.rpc_nodes
+                .iter()
+                .filter_map(|node| node.http_url.as_deref().map(|url| (node, url)))
+        };
+        with_http()
+            .find(|(node, _)| node.role.watches())
+            .or_else(|| with_http().next())
+            .ok_or_else(|| anyhow::anyhow!("None of `rpc_nodes` has an `http_url`"))
+    }
+
+    /// Maximum `sendTransaction` calls in flight to `node`, with its override applied.
+    pub fn send_concurrency_for(&self, node: &RpcNode) -> usize {
+        node.send_concurrency.unwrap_or(self.send_concurrency)
+    }
+
+    /// How many of `count` consecutive transactions are sent to `node`, at most.
+    pub fn sends_to(&self, node: &RpcNode, count: usize) -> usize {
+        match &self.propagation {
+            None => count,
+            Some(PropagationConfig::RoundRobin) => count.div_ceil(self.send_nodes().count()),
+            Some(PropagationConfig::Fixed { node: sender }) if *sender == node.name => count,
+            Some(PropagationConfig::Fixed { .. }) => 0,
+        }
+    }
+
+    /// How many of each burst's transactions are sent to `node` at once, with burst pacing.
+    pub fn burst_share(&self, node: &RpcNode) -> usize {
+        match self.pacing {
+            Some(PacingConfig::Burst { size, .. }) => self.sends_to(node, size),
+            _ => 0,
+        }
+    }
+
+    /// How many of `node`'s permits rebroadcasts must leave free for first sends: a burst's
+    /// share, and at least one so first sends never queue behind rebroadcasts.
+    pub fn first_send_reserve(&self, node: &RpcNode) -> usize {
+        self.burst_share(node).max(1)
+    }
+
+    /// Smallest `send_concurrency` `node` may have: enough for a burst's share at once, plus a
+    /// permit for rebroadcasts on top of the first send reserve.
+    fn min_send_concurrency(&self, node: &RpcNode) -> usize {
+        match self.rebroadcast {
+            Some(_) => self.first_send_reserve(node) + 1,
+            None => self.burst_share(node).max(1),
+        }
+    }
+
+    /// Rough time from the first send to the last: the pacing schedule's span, or longer if the
+    /// busiest node's sends, a `send_concurrency` at a time, take longer at an assumed round
+    /// trip.
+    fn estimated_send_span_secs(&self) -> f64 {
+        let paced = self.pacing.map_or(0.0, |pacing| {
+            self.num_transactions as f64 / pacing.target_tps()
+        });
+        self.send_nodes()
+            .map(|(node, _)| {
+                let round_trips =
+                    self.sends_to(node, self.num_transactions) / self.send_concurrency_for(node);
+                round_trips as f64 * ASSUMED_SEND_ROUND_TRIP_SECS
+            })
+            .fold(paced, f64::max)
+    }
+
+    /// `sendTransaction` options of `node`, with its overrides applied.
+    pub fn send_options_for(&self, node: &RpcNode) -> SendOptions {
+        self.send_options.with_overrides(&node.send_options)
+    }
+
+    /// Compute unit price of the `index`-th transaction.
+    pub fn compute_unit_price_for(&self, index: usize) -> Option<u64> {
+        match &self.fee_sweep {
+            Some(prices) => Some(prices[index % prices.len()]),
+            None => self.compute_unit_price,
+        }
+    }
+}
+
+/// Parse a base58 address from the config, naming `kind` in the error.
+pub fn parse_pubkey(kind: &str, value: &str) -> Result<Pubkey> {
+    Pubkey::from_str(value).map_err(|e| anyhow::anyhow!("Invalid {} {}: {}", kind, value, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_nodes(rpc_nodes: serde_json::Value) -> BenchmarkConfig {
+        serde_json::from_value(serde_json::json!({
+            "keypair_path": "payer.json",
+            "recipient": "SJDjJMwSTPt3Bs3GKBGfESLqUcKRV8M1dnbhkLatu41",
+            "amount_lamports": 1000,
+            "num_transactions": 1,
+            "rpc_nodes": rpc_nodes,
+        }))
+        .unwrap()
+    }
+
+    /// Variable lookup from `vars` instead of the process environment, which other tests read
+    /// concurrently.
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: BTreeMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn expand_env_vars_substitutes_variables() {
+        assert_eq!(
+            expand_env_vars("Bearer ${TOKEN}!", env(&[("TOKEN", "secret")])).unwrap(),
+            "Bearer secret!"
+        );
+    }
+
+    #[test]
+    fn expand_env_vars_rejects_unset_variables() {
+        let error = expand_env_vars("${UNSET}", env(&[])).unwrap_err();
+        assert!(error.to_string().contains("UNSET"));
+    }
+
+    #[test]
+    fn expand_env_vars_keeps_a_literal_dollar() {
+        let env = env(&[("HOME", "/root")]);
+        assert_eq!(
+            expand_env_vars("$5 and $HOME", &env).unwrap(),
+            "$5 and $HOME"
+        );
+        assert_eq!(expand_env_vars("ends with $", &env).unwrap(), "ends with $");
+    }
+
+    #[test]
+    fn expand_env_vars_rejects_an_unterminated_reference() {
+        let env = env(&[("TOKEN", "secret")]);
+        assert!(expand_env_vars("Bearer ${TOKEN", env).is_err());
+    }
+
+    #[test]
+    fn headers_are_resolved_as_sensitive_values() {
+        let node: RpcNode = serde_json::from_value(serde_json::json!({
+            "name": "node",
+            "headers": { "x-token": "${API_KEY}" },
+        }))
+        .unwrap();
+        let headers = node.resolve_headers(env(&[("API_KEY", "key")])).unwrap();
+        assert_eq!(headers["x-token"], "key");
+        assert!(headers["x-token"].is_sensitive());
+        assert!(node.resolve_headers(env(&[])).is_err());
+    }
+
+    #[test]
+    fn invalid_header_names_are_rejected() {
+        let node: RpcNode = serde_json::from_value(serde_json::json!({
+            "name": "node",
+            "headers": { "bad name": "value" },
+        }))
+        .unwrap();
+        assert!(node.resolve_headers(env(&[])).is_err());
+    }
+
+    #[test]
+    fn node_send_options_override_field_by_field() {
+        let mut config = config_with_nodes(serde_json::json!([
+            {
+                "name": "a",
+                "http_url": "https://a",
+                "ws_url": "wss://a",
+                "send_options": { "skip_preflight": false, "encoding": "base58" },
+            },
+            { "name": "b", "http_url": "https://b", "ws_url": "wss://b" },
+        ]));
+        config.send_options = serde_json::from_value(serde_json::json!({
+            "skip_preflight": true,
+            "max_retries": 0,
+            "encoding": "base64",
+        }))
+        .unwrap();
+
+        let options = config.send_options_for(&config.rpc_nodes[0]);
+        assert_eq!(options.skip_preflight, Some(false));
+        assert_eq!(options.encoding, Some(SendEncoding::Base58));
+        assert_eq!(options.max_retries, Some(0));
+        assert_eq!(options.preflight_commitment, None);
+        assert_eq!(
+            config.send_options_for(&config.rpc_nodes[1]),
+            config.send_options
+        );
+    }
+
+    #[test]
+    fn unpaced_send_span_grows_with_transactions_per_concurrency() {
+        let mut config = config_with_nodes(serde_json::json!([
+            { "name": "a", "http_url": "https://a", "ws_url": "wss://a" },
+            { "name": "b", "http_url": "https://b", "ws_url": "wss://b", "send_concurrency": 4 },
+        ]));
+        config.num_transactions = 10_000;
+        // 10k sends to `a`, one at a time
+        assert_eq!(config.estimated_send_span_secs(), 500.0);
+
+        config.propagation = Some(PropagationConfig::RoundRobin);
+        assert_eq!(config.estimated_send_span_secs(), 250.0);
+
+        config.pacing = Some(PacingConfig::Constant { target_tps: 10.0 });
+        assert_eq!(config.estimated_send_span_secs(), 1_000.0);
+    }
+
+    #[test]
+    fn burst_share_splits_bursts_across_round_robin_senders() {
+        let mut config = config_with_nodes(serde_json::json!([
+            { "name": "a", "http_url": "https://a", "ws_url": "wss://a" },
+            { "name": "b", "http_url": "https://b", "ws_url": "wss://b" },
+            { "name": "c", "http_url": "https://c", "ws_url": "wss://c" },
+        ]));
+        let node = config.rpc_nodes[0].clone();
+        assert_eq!(config.burst_share(&node), 0);
+
+        config.pacing = Some(PacingConfig::Burst {
+            size: 10,
+            interval_ms: 1_000,
+        });
+        assert_eq!(config.burst_share(&node), 10);
+
+        config.propagation = Some(PropagationConfig::RoundRobin);
+        assert_eq!(config.burst_share(&node), 4);
+
+        config.propagation = Some(PropagationConfig::Fixed {
+            node: "b".to_string(),
+        });
+        assert_eq!(config.burst_share(&node), 0);
+        assert_eq!(config.burst_share(&config.rpc_nodes[1]), 10);
+    }
+
+    #[test]
+    fn rebroadcasts_need_a_permit_beyond_the_first_send_reserve() {
+        let mut config = config_with_nodes(serde_json::json!([
+            { "name": "a", "http_url": "https://a", "ws_url": "wss://a" },
+        ]));
+        let node = config.rpc_nodes[0].clone();
+        assert_eq!(config.first_send_reserve(&node), 1);
+        assert_eq!(config.min_send_concurrency(&node), 1);
+
+        config.rebroadcast = Some(RebroadcastConfig {
+            interval_ms: 400,
+            max_attempts: 10,
+            stop_on_processed: true,
+        });
+        assert_eq!(config.min_send_concurrency(&node), 2);
+
+        config.pacing = Some(PacingConfig::Burst {
+            size: 10,
+            interval_ms: 1_000,
+        });
+        assert_eq!(config.first_send_reserve(&node), 10);
+        assert_eq!(config.min_send_concurrency(&node), 11);
+
+        config.rebroadcast = None;
+        assert_eq!(config.min_send_concurrency(&node), 10);
+    }
+
+    #[test]
+    fn http_node_prefers_watched_nodes() {
+        let config = config_with_nodes(serde_json::json!([
+            { "name": "staked", "http_url": "https://staked", "role": "send" },
+            { "name": "watcher", "ws_url": "wss://watcher", "role": "watch" },
+            { "name": "full", "http_url": "https://full", "ws_url": "wss://full" },
+        ]));
+        let (node, url) = config.http_node().unwrap();
+        assert_eq!((node.name.as_str(), url), ("full", "https://full"));
+    }
+
+    #[test]
+    fn http_node_falls_back_to_send_only_nodes() {
+        let config = config_with_nodes(serde_json::json!([
+            { "name": "watcher", "ws_url": "wss://watcher", "role": "watch" },
+            { "name": "staked", "http_url": "https://staked", "role": "send" },
+        ]));
+        assert_eq!(config.http_node().unwrap().0.name, "staked");
+
+        let config = config_with_nodes(serde_json::json!([
+            { "name": "watcher", "ws_url": "wss://watcher", "role": "watch" },
+        ]));
+        assert!(config.http_node().is_err());
     }
 }
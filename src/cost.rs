@@ -0,0 +1,204 @@
+use anyhow::Result;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    compute_budget, native_token::lamports_to_sol, pubkey::Pubkey,
+    transaction::VersionedTransaction,
+};
+use std::collections::BTreeMap;
+
+/// Base fee charged per transaction signature.
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+/// Compute units granted per instruction when no `SetComputeUnitLimit` is given.
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u64 = 200_000;
+const MAX_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u64 = 1_000_000;
+
+/// Estimated lamports a payer spends on a run, assuming every transaction lands.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CostEstimate {
+    pub transactions: usize,
+    /// Lamports transferred away by the transactions themselves.
+    pub transfers: u64,
+    pub base_fees: u64,
+    pub priority_fees: u64,
+    /// Rent-exempt minimum the payer has to keep to stay a valid account.
+    pub rent_reserve: u64,
+}
+
+impl CostEstimate {
+    pub fn total(&self) -> u64 {
+        self.transfers
+            .saturating_add(self.base_fees)
+            .saturating_add(self.priority_fees)
+            .saturating_add(self.rent_reserve)
+    }
+
+    /// Add the fees of `transaction`, which transfers `lamports` and pays
+    /// `compute_unit_price` micro-lamports per compute unit.
+    pub fn add_transaction(
+        &mut self,
+        transaction: &VersionedTransaction,
+        lamports: u64,
+        compute_unit_limit: Option<u32>,
+        compute_unit_price: Option<u64>,
+    ) {
+        self.transactions += 1;
+        self.transfers = self.transfers.saturating_add(lamports);
+        self.base_fees = self
+            .base_fees
+            .saturating_add(LAMPORTS_PER_SIGNATURE * transaction.signatures.len() as u64);
+        if let Some(price) = compute_unit_price {
+            let units = compute_unit_limit
+                .map(u64::from)
+                .unwrap_or_else(|| default_compute_unit_limit(transaction));
+            let micro_lamports = u128::from(price) * u128::from(units);
+            let lamports = micro_lamports.div_ceil(u128::from(MICRO_LAMPORTS_PER_LAMPORT));
+            self.priority_fees = self
+                .priority_fees
+                .saturating_add(u64::try_from(lamports).unwrap_or(u64::MAX));
+        }
+    }
+}
+
+/// Compute unit limit the runtime applies to `transaction` without `SetComputeUnitLimit`.
+fn default_compute_unit_limit(transaction: &VersionedTransaction) -> u64 {
+    let account_keys = transaction.message.static_account_keys();
+    let instructions = transaction
+        .message
+        .instructions()
+        .iter()
+        .filter(|instruction| {
+            account_keys.get(instruction.program_id_index as usize) != Some(&compute_budget::id())
+        })
+        .count() as u64;
+    (instructions * DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT).min(MAX_COMPUTE_UNIT_LIMIT)
+}
+
+/// Fetch each payer's balance and check it covers its estimated cost, logging the estimate.
+pub async fn check_balances(
+    rpc_client: &RpcClient,
+    estimates: &BTreeMap<Pubkey, CostEstimate>,
+) -> Result<()> {
+    let rent_reserve = rpc_client.get_minimum_balance_for_rent_exemption(0).await?;
+    let mut shortfalls = Vec::new();
+
+    for (payer, estimate) in estimates {
+        let estimate = CostEstimate {
+            rent_reserve,
+            ..*estimate
+        };
+        let balance = rpc_client.get_balance(payer).await?;
+        tracing::info!(
+            "Payer {}: balance {} SOL, estimated cost {} SOL for {} transactions (transfers {}, base fees {}, priority fees {}, rent reserve {} lamports)",
+            payer,
+            lamports_to_sol(balance),
+            lamports_to_sol(estimate.total()),
+            estimate.transactions,
+            estimate.transfers,
+            estimate.base_fees,
+            estimate.priority_fees,
+            estimate.rent_reserve
+        );
+        if balance < estimate.total() {
+            shortfalls.push(format!(
+                "payer {} has {} lamports but needs an estimated {} ({} short)",
+                payer,
+                balance,
+                estimate.total(),
+                estimate.total() - balance
+            ));
+        }
+    }
+
+    if !shortfalls.is_empty() {
+        anyhow::bail!("Insufficient balance: {}", shortfalls.join("; "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        compute_budget::ComputeBudgetInstruction, instruction::Instruction, message::Message,
+        system_instruction, transaction::Transaction,
+    };
+
+    fn unsigned(instructions: &[Instruction], payer: &Pubkey) -> VersionedTransaction {
+        Transaction::new_unsigned(Message::new(instructions, Some(payer))).into()
+    }
+
+    #[test]
+    fn base_fee_is_charged_per_signature() {
+        let payer = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let transaction = unsigned(
+            &[
+                system_instruction::advance_nonce_account(&Pubkey::new_unique(), &authority),
+                system_instruction::transfer(&payer, &Pubkey::new_unique(), 10),
+            ],
+            &payer,
+        );
+        assert_eq!(transaction.signatures.len(), 2);
+
+        let mut estimate = CostEstimate::default();
+        estimate.add_transaction(&transaction, 10, None, None);
+        assert_eq!(estimate.base_fees, 10_000);
+        assert_eq!(estimate.transfers, 10);
+        assert_eq!(estimate.priority_fees, 0);
+        assert_eq!(estimate.total(), 10_010);
+    }
+
+    #[test]
+    fn priority_fees_round_up_to_whole_lamports() {
+        let payer = Pubkey::new_unique();
+        let transaction = unsigned(
+            &[system_instruction::transfer(
+                &payer,
+                &Pubkey::new_unique(),
+                1,
+            )],
+            &payer,
+        );
+        let mut estimate = CostEstimate::default();
+        // 1 micro-lamport per CU for 1000 CUs is a thousandth of a lamport
+        estimate.add_transaction(&transaction, 0, Some(1_000), Some(1));
+        assert_eq!(estimate.priority_fees, 1);
+        // 1.5 lamports
+        estimate.add_transaction(&transaction, 0, Some(1_500), Some(1_000));
+        assert_eq!(estimate.priority_fees, 3);
+        assert_eq!(estimate.transactions, 2);
+    }
+
+    #[test]
+    fn default_limit_ignores_compute_budget_instructions() {
+        let payer = Pubkey::new_unique();
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+        let transaction = unsigned(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_price(1),
+                transfer.clone(),
+                transfer.clone(),
+            ],
+            &payer,
+        );
+        assert_eq!(default_compute_unit_limit(&transaction), 400_000);
+
+        let mut estimate = CostEstimate::default();
+        estimate.add_transaction(&transaction, 0, None, Some(1_000_000));
+        assert_eq!(estimate.priority_fees, 400_000);
+    }
+
+    #[test]
+    fn default_limit_is_capped() {
+        let payer = Pubkey::new_unique();
+        let instructions: Vec<Instruction> = (0..8)
+            .map(|lamports| system_instruction::transfer(&payer, &Pubkey::new_unique(), lamports))
+            .collect();
+        let transaction = unsigned(&instructions, &payer);
+        assert_eq!(
+            default_compute_unit_limit(&transaction),
+            MAX_COMPUTE_UNIT_LIMIT
+        );
+    }
+}
@@ -0,0 +1,624 @@
+use crate::clock;
+use crate::config::{
+    Commitment, FairnessConfig, MissingPenalty, NodeRole, PacingConfig, PropagationConfig,
+    RebroadcastConfig, SendOptions,
+};
+use crate::report::RunData;
+use crate::rpc::{SendErrorKind, SendRecord};
+use crate::transaction::BuildStats;
+use crate::websocket::{
+    describe_transaction_error, ConfirmationResult, MonitorResult, Reconnect, UnconfirmedReason,
+    UnconfirmedSignature,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::sync::{mpsc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+type NodeName = String;
+
+/// A single raw benchmark event. Together, the events of a run contain everything needed to
+/// rebuild its report offline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    RunStarted {
+        nodes: Vec<NodeName>,
+        missing_penalty: MissingPenalty,
+        #[serde(default)]
+        commitments: Vec<Commitment>,
+        #[serde(default)]
+        pacing: Option<PacingConfig>,
+        #[serde(default)]
+        fairness: FairnessConfig,
+        /// Effective `sendTransaction` options of each node.
+        #[serde(default)]
+        send_options: BTreeMap<NodeName, SendOptions>,
+        #[serde(default)]
+        rebroadcast: Option<RebroadcastConfig>,
+        #[serde(default)]
+        propagation: Option<PropagationConfig>,
+        /// Whether the template appended a memo with the transaction index.
+        #[serde(default)]
+        index_memo: bool,
+        /// Role of each node; nodes missing from it were both sent to and watched.
+        #[serde(default)]
+        roles: BTreeMap<NodeName, NodeRole>,
+    },
+    TransactionBuilt {
+        signature: String,
+        #[serde(default)]
+        compute_unit_price: Option<u64>,
+        #[serde(default)]
+        payer: Option<String>,
+    },
+    BatchBuilt {
+        transactions: usize,
+        elapsed_us: u64,
+        blockhash_fetches: usize,
+        #[serde(default)]
+        nonce_fetches: usize,
+    },
+    SendStarted {
+        signature: String,
+        node: NodeName,
+        /// Nodes that started sending this transaction earlier.
+        #[serde(default)]
+        position: Option<usize>,
+        /// 0 for the first send of the transaction to the node, then one per rebroadcast.
+        #[serde(default)]
+        attempt: usize,
+    },
+    SendCompleted {
+        signature: String,
+        node: NodeName,
+        error: Option<String>,
+        #[serde(default)]
+        error_kind: Option<SendErrorKind>,
+    },
+    SubscriptionAcknowledged {
+        signature: String,
+        node: NodeName,
+        #[serde(default)]
+        commitment: Commitment,
+        subscription_id: u64,
+    },
+    NotificationReceived {
+        signature: String,
+        node: NodeName,
+        #[serde(default)]
+        commitment: Commitment,
+        slot: u64,
+        err: Option<serde_json::Value>,
+        #[serde(default)]
+        resubscribed: bool,
+    },
+    SignatureUnconfirmed {
+        signature: String,
+        node: NodeName,
+        commitment: Commitment,
+        reason: UnconfirmedReason,
+    },
+    WebSocketDisconnected {
+        node: NodeName,
+        reason: String,
+        pending: usize,
+    },
+    WebSocketReconnected {
+        node: NodeName,
+        gap_us: u64,
+    },
+    MonitorFinished {
+        node: NodeName,
+        error: Option<String>,
+    },
+}
+
+/// One line of the JSONL event log.
+#[derive(Debug, Serialize, Deserialize)]
+struct EventRecord {
+    /// Microseconds on the `clock` timeline.
+    timestamp_us: u64,
+    #[serde(flatten)]
+    event: Event,
+}
+
+/// Append-only JSONL event log shared by the send tasks and WebSocket monitors. Events are
+/// handed to a background thread that serializes and writes them, so recording one never waits
+/// on file I/O or on other recorders.
+pub struct EventLog {
+    /// `None` asks the writer to stop.
+    sender: mpsc::Sender<Option<EventRecord>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl EventLog {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create event log {:?}", path))?;
+        let (sender, receiver) = mpsc::channel();
+        let writer = std::thread::Builder::new()
+            .name("event-log".to_string())
+            .spawn(move || write_events(LineWriter::new(file), receiver))
+            .context("Failed to start the event log writer")?;
+        Ok(Self {
+            sender,
+            writer: Mutex::new(Some(writer)),
+        })
+    }
+
+    /// Record `event` as having happened at `timestamp_us` (see `clock::now_us`).
+    pub fn record_at(&self, timestamp_us: u64, event: Event) {
+        if self
+            .sender
+            .send(Some(EventRecord {
+                timestamp_us,
+                event,
+            }))
+            .is_err()
+        {
+            tracing::warn!("Event log already closed, dropping event");
+        }
+    }
+
+    /// Record `event` as happening now.
+    pub fn record(&self, event: Event) {
+        self.record_at(clock::now_us(), event);
+    }
+
+    /// Write out every event recorded so far and stop the writer. Later events are dropped.
+    pub fn close(&self) {
+        let writer = self.writer.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(writer) = writer {
+            // The receiver lives until the writer returns
+            let _ = self.sender.send(None);
+            if writer.join().is_err() {
+                tracing::warn!("Event log writer panicked");
+            }
+        }
+    }
+}
+
+impl Drop for EventLog {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Write records from `receiver` to `writer`, one JSON object per line, until asked to stop.
+fn write_events(mut writer: LineWriter<File>, receiver: mpsc::Receiver<Option<EventRecord>>) {
+    while let Ok(Some(record)) = receiver.recv() {
+        let line = match serde_json::to_string(&record) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!("Failed to serialize event: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = writeln!(writer, "{}", line) {
+            tracing::warn!("Failed to write event log: {}", e);
+        }
+    }
+}
+
+/// The inputs of `report::Report::build`, reconstructed from an event log.
+pub struct Replay {
+    pub missing_penalty: MissingPenalty,
+    pub commitments: Vec<Commitment>,
+    pub run: RunData,
+}
+
+impl Replay {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open event log {:?}", path))?;
+        Self::from_reader(BufReader::new(file))
+            .with_context(|| format!("Failed to replay event log {:?}", path))
+    }
+
+    /// Replay the JSONL events read from `reader`.
+    pub fn from_reader(reader: impl BufRead) -> Result<Self> {
+        let mut missing_penalty = MissingPenalty::default();
+        let mut commitments = Vec::new();
+        let mut pacing = None;
+        let mut fairness = FairnessConfig::default();
+        let mut send_options = BTreeMap::new();
+        let mut rebroadcast = None;
+        let mut propagation = None;
+        let mut index_memo = false;
+        let mut roles: BTreeMap<NodeName, NodeRole> = BTreeMap::new();
+        let mut nodes: Vec<NodeName> = Vec::new();
+        let mut failed_nodes: BTreeSet<NodeName> = BTreeSet::new();
+        let mut expected_signatures = Vec::new();
+        let mut build = None;
+        let mut compute_unit_prices = BTreeMap::new();
+        let mut payers = BTreeMap::new();
+        // (signature, node) -> (send start, position in send order, attempt); sends of the same
+        // transaction to the same node never overlap
+        let mut send_starts: HashMap<(String, NodeName), (u64, Option<usize>, usize)> =
+            HashMap::new();
+        let mut send_records = Vec::new();
+        let mut results: BTreeMap<NodeName, MonitorResult> = BTreeMap::new();
+
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: EventRecord = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid event on line {}", line_number + 1))?;
+
+            match record.event {
+                Event::RunStarted {
+                    nodes: run_nodes,
+                    missing_penalty: penalty,
+                    commitments: run_commitments,
+                    pacing: run_pacing,
+                    fairness: run_fairness,
+                    send_options: run_send_options,
+                    rebroadcast: run_rebroadcast,
+                    propagation: run_propagation,
+                    index_memo: run_index_memo,
+                    roles: run_roles,
+                } => {
+                    nodes = run_nodes;
+                    missing_penalty = penalty;
+                    commitments = run_commitments;
+                    pacing = run_pacing;
+                    fairness = run_fairness;
+                    send_options = run_send_options;
+                    rebroadcast = run_rebroadcast;
+                    propagation = run_propagation;
+                    index_memo = run_index_memo;
+                    roles = run_roles;
+                }
+                Event::TransactionBuilt {
+                    signature,
+                    compute_unit_price,
+                    payer,
+                } => {
+                    if let Some(price) = compute_unit_price {
+                        compute_unit_prices.insert(signature.clone(), price);
+                    }
+                    if let Some(payer) = payer {
+                        payers.insert(signature.clone(), payer);
+                    }
+                    expected_signatures.push(signature);
+                }
+                Event::BatchBuilt {
+                    transactions,
+                    elapsed_us,
+                    blockhash_fetches,
+                    nonce_fetches,
+                } => {
+                    build = Some(BuildStats {
+                        transactions,
+                        elapsed: Duration::from_micros(elapsed_us),
+                        blockhash_fetches,
+                        nonce_fetches,
+                    });
+                }
+                Event::SendStarted {
+                    signature,
+                    node,
+                    position,
+                    attempt,
+                } => {
+                    send_starts.insert((signature, node), (record.timestamp_us, position, attempt));
+                }
+                Event::SendCompleted {
+                    signature,
+                    node,
+                    error,
+                    error_kind,
+                } => {
+                    if let Some((send_start_us, send_position, attempt)) =
+                        send_starts.remove(&(signature.clone(), node.clone()))
+                    {
+                        send_records.push(SendRecord {
+                            signature,
+                            node_name: node,
+                            send_start_us,
+                            send_ack_us: record.timestamp_us,
+                            send_position,
+                            attempt,
+                            error: error_kind
+                                .or_else(|| error.as_deref().map(SendErrorKind::from_message)),
+                        });
+                    }
+                }
+                Event::SubscriptionAcknowledged { .. } => {}
+                Event::NotificationReceived {
+                    signature,
+                    node,
+                    commitment,
+                    slot,
+                    err,
+                    resubscribed,
+                } => {
+                    results
+                        .entry(node)
+                        .or_default()
+                        .confirmations
+                        .push(ConfirmationResult {
+                            signature,
+                            commitment,
+                            timestamp_us: record.timestamp_us,
+                            resubscribed,
+                            slot,
+                            err: describe_transaction_error(err.as_ref()),
+                        });
+                }
+                Event::WebSocketDisconnected { node, .. } => {
+                    results.entry(node).or_default().reconnects.push(Reconnect {
+                        disconnected_us: record.timestamp_us,
+                        reconnected_us: None,
+                    });
+                }
+                Event::WebSocketReconnected { node, .. } => {
+                    if let Some(reconnect) = results.entry(node).or_default().reconnects.last_mut()
+                    {
+                        reconnect.reconnected_us = Some(record.timestamp_us);
+                    }
+                }
+                Event::SignatureUnconfirmed {
+                    signature,
+                    node,
+                    commitment,
+                    reason,
+                } => {
+                    results
+                        .entry(node)
+                        .or_default()
+                        .unconfirmed
+                        .push(UnconfirmedSignature {
+                            signature,
+                            commitment,
+                            reason,
+                        });
+                }
+                Event::MonitorFinished { node, error } => {
+                    if error.is_some() {
+                        failed_nodes.insert(node);
+                    }
+                }
+            }
+        }
+
+        if commitments.is_empty() {
+            commitments.push(Commitment::default());
+        }
+
+        // Mirror a live run: only watched nodes are monitored, and nodes whose monitor failed
+        // miss every signature.
+        let node_results = nodes
+            .into_iter()
+            .filter(|node| roles.get(node).copied().unwrap_or_default().watches())
+            .map(|node| {
+                let result = if failed_nodes.contains(&node) {
+                    MonitorResult::failed(&expected_signatures, &commitments)
+                } else {
+                    results.remove(&node).unwrap_or_default()
+                };
+                (node, result)
+            })
+            .collect();
+
+        Ok(Self {
+            missing_penalty,
+            commitments,
+            run: RunData {
+                expected_signatures,
+                compute_unit_prices,
+                payers,
+                build,
+                pacing,
+                fairness,
+                send_options,
+                rebroadcast,
+                propagation,
+                index_memo,
+                roles,
+                send_records,
+                node_results,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Report;
+
+    /// `fast` and `slow` are sent to and watched, `staked` is only sent to and `broken`'s monitor
+    /// failed. `slow` never sees `sig2` and reconnects once.
+    const FIXTURE: &str = r#"
+{"timestamp_us":0,"event":"run_started","nodes":["fast","slow","staked","broken"],"missing_penalty":{"mode":"max_observed"},"commitments":["processed"],"roles":{"staked":"send"}}
+{"timestamp_us":10,"event":"transaction_built","signature":"sig1","compute_unit_price":1000,"payer":"payer1"}
+{"timestamp_us":11,"event":"transaction_built","signature":"sig2","payer":"payer1"}
+{"timestamp_us":100,"event":"send_started","signature":"sig1","node":"fast","position":0}
+{"timestamp_us":105,"event":"send_started","signature":"sig1","node":"staked","position":1}
+{"timestamp_us":140,"event":"send_completed","signature":"sig1","node":"fast","error":null}
+{"timestamp_us":150,"event":"send_completed","signature":"sig1","node":"staked","error":"HTTP status client error (429 Too Many Requests)"}
+{"timestamp_us":200,"event":"send_started","signature":"sig1","node":"fast","position":null,"attempt":1}
+{"timestamp_us":230,"event":"send_completed","signature":"sig1","node":"fast","error":null,"error_kind":"already_processed"}
+{"timestamp_us":300,"event":"send_started","signature":"sig2","node":"fast","position":0}
+{"timestamp_us":320,"event":"send_completed","signature":"sig2","node":"fast","error":null}
+{"timestamp_us":1000,"event":"notification_received","signature":"sig1","node":"fast","slot":7,"err":null}
+{"timestamp_us":1300,"event":"notification_received","signature":"sig1","node":"slow","slot":7,"err":null}
+{"timestamp_us":1400,"event":"notification_received","signature":"sig1","node":"broken","slot":7,"err":null}
+{"timestamp_us":1500,"event":"web_socket_disconnected","node":"slow","reason":"stream ended","pending":1}
+{"timestamp_us":1700,"event":"web_socket_reconnected","node":"slow","gap_us":200}
+{"timestamp_us":2000,"event":"notification_received","signature":"sig2","node":"fast","slot":9,"err":{"InstructionError":[0,{"Custom":1}]}}
+{"timestamp_us":9000,"event":"signature_unconfirmed","signature":"sig2","node":"slow","commitment":"processed","reason":{"kind":"signature_timeout","after_ms":8}}
+{"timestamp_us":9100,"event":"monitor_finished","node":"fast","error":null}
+{"timestamp_us":9200,"event":"monitor_finished","node":"slow","error":null}
+{"timestamp_us":9300,"event":"monitor_finished","node":"broken","error":"connection refused"}
+"#;
+
+    fn replay() -> Replay {
+        Replay::from_reader(FIXTURE.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn replay_pairs_sends_with_their_completion() {
+        let run = replay().run;
+        let sends: Vec<_> = run
+            .send_records
+            .iter()
+            .map(|record| {
+                (
+                    record.signature.as_str(),
+                    record.node_name.as_str(),
+                    record.send_start_us,
+                    record.send_ack_us,
+                    record.attempt,
+                    record.error,
+                )
+            })
+            .collect();
+        assert_eq!(
+            sends,
+            [
+                ("sig1", "fast", 100, 140, 0, None),
+                (
+                    "sig1",
+                    "staked",
+                    105,
+                    150,
+                    0,
+                    Some(SendErrorKind::RateLimited)
+                ),
+                (
+                    "sig1",
+                    "fast",
+                    200,
+                    230,
+                    1,
+                    Some(SendErrorKind::AlreadyProcessed)
+                ),
+                ("sig2", "fast", 300, 320, 0, None),
+            ]
+        );
+        assert_eq!(run.expected_signatures, ["sig1", "sig2"]);
+        assert_eq!(run.compute_unit_prices.get("sig1"), Some(&1000));
+        assert_eq!(run.payers.get("sig2").map(String::as_str), Some("payer1"));
+    }
+
+    #[test]
+    fn replay_keeps_watched_nodes_and_fails_every_signature_of_a_failed_monitor() {
+        let replay = replay();
+        assert_eq!(replay.commitments, [Commitment::Processed]);
+        let nodes: Vec<_> = replay
+            .run
+            .node_results
+            .iter()
+            .map(|(node, _)| node.as_str())
+            .collect();
+        assert_eq!(nodes, ["fast", "slow", "broken"]);
+
+        let slow = &replay.run.node_results[1].1;
+        assert_eq!(slow.confirmations.len(), 1);
+        assert_eq!(slow.unconfirmed.len(), 1);
+        assert_eq!(slow.reconnects[0].disconnected_us, 1500);
+        assert_eq!(slow.reconnects[0].reconnected_us, Some(1700));
+
+        let broken = &replay.run.node_results[2].1;
+        assert!(broken.confirmations.is_empty());
+        let unconfirmed: Vec<_> = broken
+            .unconfirmed
+            .iter()
+            .map(|unconfirmed| (unconfirmed.signature.as_str(), &unconfirmed.reason))
+            .collect();
+        assert_eq!(
+            unconfirmed,
+            [
+                ("sig1", &UnconfirmedReason::MonitorFailed),
+                ("sig2", &UnconfirmedReason::MonitorFailed),
+            ]
+        );
+    }
+
+    #[test]
+    fn replayed_report_matches_the_run() {
+        let replay = replay();
+        let report = Report::build(&replay.run, &replay.missing_penalty, &replay.commitments);
+        assert_eq!(report.succeeded_signatures, 1);
+        assert_eq!(report.failed_signatures, 1);
+
+        let ranking: Vec<_> = report
+            .summary
+            .iter()
+            .map(|summary| (summary.rank, summary.node.as_str(), summary.score_us))
+            .collect();
+        // `slow` is 300 μs behind on sig1 and charged that again for missing sig2, and `broken`
+        // is charged for missing both
+        assert_eq!(
+            ranking,
+            [
+                (Some(1), "fast", Some(0)),
+                (Some(2), "slow", Some(600)),
+                (Some(3), "broken", Some(600)),
+                (None, "staked", None),
+            ]
+        );
+        let fast = &report.summary[0];
+        assert_eq!((fast.sends, fast.sent_first), (3, 2));
+        assert_eq!(
+            report.summary[3].send_errors[&SendErrorKind::RateLimited],
+            1
+        );
+    }
+
+    #[test]
+    fn event_log_round_trips_through_replay() {
+        let path = std::env::temp_dir().join(format!("usopp-events-{}.jsonl", std::process::id()));
+        let event_log = EventLog::create(&path).unwrap();
+        event_log.record_at(
+            5,
+            Event::TransactionBuilt {
+                signature: "sig1".to_string(),
+                compute_unit_price: None,
+                payer: None,
+            },
+        );
+        event_log.record_at(
+            6,
+            Event::SendStarted {
+                signature: "sig1".to_string(),
+                node: "fast".to_string(),
+                position: Some(0),
+                attempt: 0,
+            },
+        );
+        event_log.record_at(
+            9,
+            Event::SendCompleted {
+                signature: "sig1".to_string(),
+                node: "fast".to_string(),
+                error: None,
+                error_kind: None,
+            },
+        );
+        event_log.close();
+        // Dropped once closed
+        event_log.record(Event::WebSocketReconnected {
+            node: "fast".to_string(),
+            gap_us: 1,
+        });
+
+        let replay = Replay::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        let run = replay.unwrap().run;
+        assert_eq!(run.expected_signatures, ["sig1"]);
+        assert_eq!(run.send_records.len(), 1);
+        assert_eq!(run.send_records[0].send_start_us, 6);
+        assert_eq!(run.send_records[0].send_ack_us, 9);
+    }
+}
@@ -1,81 +1,316 @@
+mod clock;
 mod config;
+mod cost;
+mod events;
+mod nonce;
+mod pacing;
 mod report;
 mod rpc;
+mod template;
 mod transaction;
 mod websocket;
 
 use anyhow::Result;
 use clap::Parser;
-use config::{BenchmarkConfig, CliArgs};
-use rpc::RpcClientManager;
+use config::{
+    parse_pubkey, BenchmarkConfig, CliArgs, Command, DurableNonceConfig, FairnessConfig, NodeRole,
+    OutputFormat, SendOptions, TransactionVersion,
+};
+use cost::CostEstimate;
+use events::{Event, EventLog, Replay};
+use rpc::{RpcClientManager, SendTracker};
+use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey;
-use solana_sdk::signature::read_keypair_file;
-use std::str::FromStr;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::task::JoinHandle;
-use websocket::{ConfirmationResult, WebSocketHandle};
+use transaction::{BlockhashSource, TransactionFormat, TransactionParams};
+use websocket::{MonitorResult, MonitorSettings, WebSocketHandle};
 
 type NodeName = String;
-type NodeConfirmationResults = Vec<ConfirmationResult>;
-type WebSocketTaskResult = Result<(NodeName, NodeConfirmationResults)>;
+type WebSocketTaskResult = Result<(NodeName, MonitorResult)>;
 type WebSocketJoinHandle = JoinHandle<WebSocketTaskResult>;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize logging
     tracing_subscriber::fmt::init();
+    // Anchor the monotonic clock shared by send and confirmation timestamps
+    clock::now_us();
 
     // Parse command line arguments
     let args = CliArgs::parse();
+    // Fail before a run rather than after it
+    if args.output_format == OutputFormat::Csv && args.output.is_none() {
+        anyhow::bail!("--output-format csv requires --output");
+    }
 
-    // Load configuration
-    let config = BenchmarkConfig::from_file(&args.config)?;
+    match &args.command {
+        Some(Command::Report { from }) => report_from_event_log(&args, from),
+        Some(Command::CreateNonceAccounts { count }) => create_nonce_accounts(&args, *count).await,
+        None => run_benchmark(&args).await,
+    }
+}
 
-    // Parse recipient pubkey
-    let recipient_pubkey = pubkey::Pubkey::from_str(&config.recipient)
-        .map_err(|e| anyhow::anyhow!("Invalid recipient pubkey: {}", e))?;
+fn load_config(args: &CliArgs) -> Result<BenchmarkConfig> {
+    let config_path = args
+        .config
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("--config is required"))?;
+    BenchmarkConfig::from_file(config_path)
+}
 
-    // Load keypair
-    let keypair = read_keypair_file(&config.keypair_path).map_err(|e| {
+fn read_payer_keypair(config: &BenchmarkConfig) -> Result<Keypair> {
+    read_keypair_file(&config.keypair_path).map_err(|e| {
         anyhow::anyhow!(
             "Failed to read keypair from {:?}: {}",
             config.keypair_path,
             e
         )
-    })?;
+    })
+}
+
+/// All payer keypairs of a run: `keypair_path`, then `payer_keypair_paths`, then the keypairs
+/// in `payer_keypair_dir`.
+fn read_payer_keypairs(config: &BenchmarkConfig) -> Result<Vec<Keypair>> {
+    let mut paths = vec![config.keypair_path.clone()];
+    paths.extend(config.payer_keypair_paths.iter().cloned());
+    if let Some(dir) = &config.payer_keypair_dir {
+        let mut dir_paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map_err(|e| anyhow::anyhow!("Failed to read payer directory {:?}: {}", dir, e))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        dir_paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+        dir_paths.sort();
+        paths.extend(dir_paths);
+    }
+
+    let mut payers: Vec<Keypair> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let keypair = read_keypair_file(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read keypair from {:?}: {}", path, e))?;
+        // The same keypair listed twice would only add lock contention back
+        if !payers
+            .iter()
+            .any(|payer| payer.pubkey() == keypair.pubkey())
+        {
+            payers.push(keypair);
+        }
+    }
+    Ok(payers)
+}
+
+/// Create `count` nonce accounts for durable nonce runs and print the config entry using them.
+async fn create_nonce_accounts(args: &CliArgs, count: usize) -> Result<()> {
+    let config = load_config(args)?;
+    let payer = read_payer_keypair(&config)?;
+    let (http_node, http_url) = config.http_node()?;
+    let rpc_client = rpc::http_client(
+        http_url,
+        &http_node.resolved_headers,
+        CommitmentConfig::confirmed(),
+    )?;
+    let created = nonce::create_nonce_accounts(&rpc_client, &payer, count).await?;
+    // Print whatever was created, even on failure, so no funded account is lost track of
+    let entry = DurableNonceConfig {
+        accounts: created
+            .accounts
+            .iter()
+            .map(|account| account.to_string())
+            .collect(),
+    };
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&serde_json::json!({ "durable_nonce": entry }))?
+    );
+    match created.error {
+        Some(e) => Err(e.context(format!(
+            "Created only {} of {} nonce accounts; the entry above lists them",
+            created.accounts.len(),
+            count
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn transaction_format(config: &BenchmarkConfig) -> Result<TransactionFormat> {
+    match config.transaction_version {
+        TransactionVersion::Legacy => Ok(TransactionFormat::Legacy),
+        TransactionVersion::V0 => Ok(TransactionFormat::V0 {
+            lookup_tables: config
+                .address_lookup_tables
+                .iter()
+                .map(|table| parse_pubkey("address lookup table", table))
+                .collect::<Result<_>>()?,
+        }),
+    }
+}
+
+fn blockhash_source(config: &BenchmarkConfig) -> Result<BlockhashSource> {
+    match &config.durable_nonce {
+        Some(durable_nonce) => {
+            let accounts = durable_nonce
+                .accounts
+                .iter()
+                .map(|account| parse_pubkey("nonce account", account))
+                .collect::<Result<_>>()?;
+            Ok(BlockhashSource::DurableNonce(accounts))
+        }
+        None => Ok(BlockhashSource::Recent(config.blockhash_refresh.clone())),
+    }
+}
+
+/// Rebuild the report of a previous run from its event log.
+fn report_from_event_log(args: &CliArgs, event_log_path: &Path) -> Result<()> {
+    tracing::info!("Rebuilding benchmark report from {:?}...", event_log_path);
+    let replay = Replay::from_file(event_log_path)?;
+    let report = report::Report::build(&replay.run, &replay.missing_penalty, &replay.commitments);
+    report::write_report(&report, args.output_format, args.output.as_deref())
+}
+
+async fn run_benchmark(args: &CliArgs) -> Result<()> {
+    // Load configuration
+    let config = load_config(args)?;
+
+    // Open the raw event log
+    let event_log_path = args.event_log.clone().unwrap_or_else(|| {
+        let unix_secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        PathBuf::from(format!("usopp-events-{}.jsonl", unix_secs))
+    });
+    let event_log = Arc::new(EventLog::create(&event_log_path)?);
+    tracing::info!("Recording raw events to {:?}", event_log_path);
+    let send_options: BTreeMap<NodeName, SendOptions> = config
+        .send_nodes()
+        .map(|(node, _)| (node.name.clone(), config.send_options_for(node)))
+        .collect();
+    let roles: BTreeMap<NodeName, NodeRole> = config
+        .rpc_nodes
+        .iter()
+        .map(|node| (node.name.clone(), node.role))
+        .collect();
+    event_log.record(Event::RunStarted {
+        nodes: config.rpc_nodes.iter().map(|n| n.name.clone()).collect(),
+        missing_penalty: config.missing_penalty.clone(),
+        commitments: config.commitments.clone(),
+        pacing: config.pacing,
+        fairness: config.fairness,
+        send_options: send_options.clone(),
+        rebroadcast: config.rebroadcast,
+        propagation: config.propagation.clone(),
+        index_memo: config.template.adds_index_memo(),
+        roles: roles.clone(),
+    });
+
+    // Load payer keypairs
+    let payers = read_payer_keypairs(&config)?;
+    tracing::info!("Using {} payer keypair(s)", payers.len());
+    let template = template::from_config(&config, &payers)?;
 
     // Pre-build all transactions
-    let mut transactions = Vec::new();
+    tracing::info!("Building {} transactions...", config.num_transactions);
+    let (http_node, http_url) = config.http_node()?;
+    let builder = transaction::TransactionBuilder::new(
+        // Build through a node that serves regular RPC methods, not a send-only endpoint
+        rpc::http_client(
+            http_url,
+            &http_node.resolved_headers,
+            CommitmentConfig::confirmed(),
+        )?,
+        payers,
+        template,
+        blockhash_source(&config)?,
+        transaction_format(&config)?,
+        config.compute_unit_limit,
+    );
+    let params: Vec<TransactionParams> = (0..config.num_transactions)
+        .map(|i| TransactionParams {
+            index: i,
+            compute_unit_price: config.compute_unit_price_for(i),
+        })
+        .collect();
+    let (transactions, build_stats, blockhash_expiry) = builder.build_batch(&params).await?;
     // Also collect signatures for WebSocket monitoring
-    let mut transaction_signatures = Vec::new();
+    let transaction_signatures: Vec<_> = transactions.iter().map(|tx| tx.signatures[0]).collect();
+    let transaction_payers: Vec<String> = params
+        .iter()
+        .map(|params| builder.payer(params.index).pubkey().to_string())
+        .collect();
+    for ((signature, params), payer) in transaction_signatures
+        .iter()
+        .zip(&params)
+        .zip(&transaction_payers)
+    {
+        event_log.record(Event::TransactionBuilt {
+            signature: signature.to_string(),
+            compute_unit_price: params.compute_unit_price,
+            payer: Some(payer.clone()),
+        });
+    }
+    event_log.record(Event::BatchBuilt {
+        transactions: build_stats.transactions,
+        elapsed_us: build_stats.elapsed.as_micros() as u64,
+        blockhash_fetches: build_stats.blockhash_fetches,
+        nonce_fetches: build_stats.nonce_fetches,
+    });
+    tracing::info!(
+        "All {} transactions built in {:?} ({:.0} tx/s, {} blockhash fetches, {} nonce fetches).",
+        build_stats.transactions,
+        build_stats.elapsed,
+        build_stats.transactions_per_second(),
+        build_stats.blockhash_fetches,
+        build_stats.nonce_fetches
+    );
 
-    tracing::info!("Building {} transactions...", config.num_transactions);
-    for i in 0..config.num_transactions {
-        let amount = config.amount_lamports + i as u64; // Ensure unique amount for unique hash if needed
-        let builder = transaction::TransactionBuilder::new(
-            config.rpc_nodes[0].http_url.clone(), // Using first node for tx building context
-            keypair.insecure_clone(),
-            recipient_pubkey,
-            amount,
-        );
-        let built_transaction = builder.build_transaction().await?;
-        transaction_signatures.push(built_transaction.signatures[0]);
-        transactions.push(built_transaction);
+    // Make sure every payer can afford the run before sending anything
+    let mut estimates: BTreeMap<pubkey::Pubkey, CostEstimate> = BTreeMap::new();
+    for (transaction, params) in transactions.iter().zip(&params) {
+        estimates
+            .entry(builder.payer(params.index).pubkey())
+            .or_default()
+            .add_transaction(
+                transaction,
+                builder.template().lamports(params.index),
+                config.compute_unit_limit,
+                params.compute_unit_price,
+            );
     }
-    tracing::info!("All {} transactions built.", transactions.len());
+    let balance_client = rpc::http_client(
+        http_url,
+        &http_node.resolved_headers,
+        CommitmentConfig::confirmed(),
+    )?;
+    cost::check_balances(&balance_client, &estimates).await?;
 
     // Spawn WebSocket monitoring threads
-    let mut ws_handles: Vec<WebSocketJoinHandle> = Vec::new(); // Using type alias
+    let mut ws_handles: Vec<(NodeName, WebSocketJoinHandle)> = Vec::new(); // Using type alias
     tracing::info!(
         "Spawning WebSocket monitoring threads for {} RPC nodes and {} signatures...",
-        config.rpc_nodes.len(),
+        config.watch_nodes().count(),
         transaction_signatures.len()
     );
 
-    for rpc_node_config in &config.rpc_nodes {
+    let send_tracker = SendTracker::default();
+    let monitor_settings = MonitorSettings {
+        commitments: config.commitments.clone(),
+        signature_timeout: Duration::from_millis(config.signature_timeout_ms),
+        monitor_timeout: config.monitor_timeout_ms.map(Duration::from_millis),
+        reconnect: config.reconnect.clone(),
+    };
+
+    for (rpc_node_config, ws_url) in config.watch_nodes() {
         let node_name = rpc_node_config.name.clone();
-        let node_ws_url = rpc_node_config.ws_url.clone();
+        let node_ws_url = ws_url.to_string();
+        let headers = rpc_node_config.resolved_headers.clone();
         let signatures_clone = transaction_signatures.clone();
-        // let ws_result_tx_clone = ws_result_tx.clone(); // Removed
+        let monitor_settings = monitor_settings.clone();
+        let event_log = Arc::clone(&event_log);
+        let send_tracker = send_tracker.clone();
 
         let handle = tokio::spawn(async move {
             tracing::info!(
@@ -83,17 +318,25 @@ async fn main() -> Result<()> {
                 node_ws_url,
                 node_name
             );
-            let ws_handle = WebSocketHandle::new(node_ws_url.clone(), signatures_clone); // tx_clone removed
+            let ws_handle = WebSocketHandle::new(
+                node_name.clone(),
+                node_ws_url.clone(),
+                &headers,
+                signatures_clone,
+                monitor_settings,
+                Arc::clone(&event_log),
+                send_tracker,
+            )?;
             match ws_handle.monitor_confirmation().await {
-                // Now returns Result<Vec<...>>
-                Ok(confirmations) => {
+                Ok(monitor_result) => {
                     tracing::info!(
-                        "WebSocket monitoring for {} ({}) completed, {} confirmations received.",
+                        "WebSocket monitoring for {} ({}) completed, {} confirmations received, {} unconfirmed.",
                         node_ws_url,
                         node_name,
-                        confirmations.len()
+                        monitor_result.confirmations.len(),
+                        monitor_result.unconfirmed.len()
                     );
-                    Ok((node_name, confirmations)) // Return node_name along with confirmations
+                    Ok((node_name, monitor_result)) // Return node_name along with its results
                 }
                 Err(e) => {
                     tracing::error!(
@@ -106,51 +349,129 @@ async fn main() -> Result<()> {
                 }
             }
         });
-        ws_handles.push(handle);
+        ws_handles.push((rpc_node_config.name.clone(), handle));
     }
 
     // Initialize RPC clients (HTTP)
-    let rpc_http_urls: Vec<String> = config
-        .rpc_nodes
-        .iter()
-        .map(|node| node.http_url.clone())
-        .collect();
-    let rpc_manager = RpcClientManager::new(rpc_http_urls);
+    let rpc_manager = RpcClientManager::new(&config, Arc::clone(&event_log), send_tracker.clone())?;
 
     // Send transactions via HTTP
     tracing::info!(
         "Sending {} transactions to {} RPC nodes via HTTP...",
         transactions.len(),
-        config.rpc_nodes.len()
+        config.send_nodes().count()
     );
-    // This is currently synchronous in its internal implementation, but it's fine.
-    rpc_manager.send_transactions(&transactions);
+    let schedule = config
+        .pacing
+        .map(|pacing| {
+            tracing::info!("Pacing sends: {}", pacing);
+            pacing::schedule(&pacing, transactions.len())
+        })
+        .transpose()?;
+    if let Some(propagation) = &config.propagation {
+        tracing::info!(
+            "Propagation mode: each transaction goes to a single node ({})",
+            propagation
+        );
+    }
+    if let Some(rebroadcast) = &config.rebroadcast {
+        tracing::info!("Rebroadcasting transactions {}", rebroadcast);
+    }
+    if config.fairness != FairnessConfig::default() {
+        tracing::info!("Send fairness: {}", config.fairness);
+    }
+    let send_records = rpc_manager
+        .send_transactions(&transactions, schedule.as_deref(), &blockhash_expiry)
+        .await;
+    let failed_sends = send_records
+        .iter()
+        .filter(|record| record.error.is_some())
+        .count();
+    if failed_sends > 0 {
+        tracing::warn!(
+            "{} of {} sends failed; see the send errors in the report.",
+            failed_sends,
+            send_records.len()
+        );
+    }
     tracing::info!("All transactions sent via HTTP.");
 
-    // Collect results from WebSocket threads by awaiting handles
-    let mut all_node_confirmations: Vec<(NodeName, NodeConfirmationResults)> = Vec::new();
-    for handle in ws_handles {
-        match handle.await {
+    // Collect results from WebSocket threads by awaiting handles. A node whose monitor failed
+    // misses every signature instead of dropping out of the report.
+    let expected_signatures: Vec<String> = transaction_signatures
+        .iter()
+        .map(|signature| signature.to_string())
+        .collect();
+    let mut node_results: Vec<(NodeName, MonitorResult)> = Vec::new();
+    for (node_name, handle) in ws_handles {
+        // Every outcome is logged here, once, so a replay fails the same nodes
+        let error = match handle.await {
             // This is Result<WebSocketTaskResult, JoinError>
             Ok(Ok(node_data)) => {
-                all_node_confirmations.push(node_data);
+                node_results.push(node_data);
+                None
             }
             Ok(Err(e)) => {
                 tracing::error!("A WebSocket monitoring task returned an error: {}", e);
+                Some(e.to_string())
             }
             Err(e) => {
                 tracing::error!(
                     "A WebSocket monitoring task failed to join (panicked): {}",
                     e
                 );
+                Some(e.to_string())
             }
+        };
+        if error.is_some() {
+            node_results.push((
+                node_name.clone(),
+                MonitorResult::failed(&expected_signatures, &config.commitments),
+            ));
         }
+        event_log.record(Event::MonitorFinished {
+            node: node_name,
+            error,
+        });
     }
 
     // Generate and print the report
     tracing::info!("Generating benchmark report...");
-    let report_markdown = report::generate_report_markdown(&all_node_confirmations);
-    tracing::info!("{}", report_markdown);
+    let run = report::RunData {
+        expected_signatures,
+        compute_unit_prices: transaction_signatures
+            .iter()
+            .zip(&params)
+            .filter_map(|(signature, params)| {
+                params
+                    .compute_unit_price
+                    .map(|price| (signature.to_string(), price))
+            })
+            .collect(),
+        payers: transaction_signatures
+            .iter()
+            .map(|signature| signature.to_string())
+            .zip(transaction_payers)
+            .collect(),
+        build: Some(build_stats),
+        pacing: config.pacing,
+        fairness: config.fairness,
+        send_options,
+        rebroadcast: config.rebroadcast,
+        propagation: config.propagation.clone(),
+        index_memo: config.template.adds_index_memo(),
+        roles,
+        send_records,
+        node_results,
+    };
+    let report = report::Report::build(&run, &config.missing_penalty, &config.commitments);
+    report::write_report(&report, args.output_format, args.output.as_deref())?;
+    event_log.close();
+    tracing::info!(
+        "Raw events saved to {:?}; rebuild the report with `usopp report --from {}`",
+        event_log_path,
+        event_log_path.display()
+    );
 
     Ok(())
 }
@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::nonce_utils::nonblocking::data_from_account;
+use solana_client::rpc_request::MAX_MULTIPLE_ACCOUNTS;
+use solana_sdk::{
+    hash::Hash,
+    nonce::State,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+
+/// Nonce accounts created concurrently.
+const CREATE_CONCURRENCY: usize = 16;
+
+/// Nonce accounts created by `create_nonce_accounts`, and the error that stopped it early, if
+/// any. The accounts created before the error are funded and must not be forgotten; the error
+/// names the accounts whose creation was not confirmed, which may be funded too.
+pub struct CreatedNonceAccounts {
+    pub accounts: Vec<Pubkey>,
+    pub error: Option<anyhow::Error>,
+}
+
+/// Create `count` durable nonce accounts funded by `payer`, with `payer` as nonce authority.
+/// Each address is logged as soon as its creation is confirmed; creation stops after the first
+/// chunk with a failure.
+pub async fn create_nonce_accounts(
+    rpc_client: &RpcClient,
+    payer: &Keypair,
+    count: usize,
+) -> Result<CreatedNonceAccounts> {
+    let rent = rpc_client
+        .get_minimum_balance_for_rent_exemption(State::size())
+        .await?;
+    tracing::info!(
+        "Creating {} nonce accounts ({} lamports each)...",
+        count,
+        rent
+    );
+
+    let mut created = Vec::with_capacity(count);
+    let mut error = None;
+    let nonce_keypairs: Vec<Keypair> = (0..count).map(|_| Keypair::new()).collect();
+    for chunk in nonce_keypairs.chunks(CREATE_CONCURRENCY) {
+        let results = join_all(
+            chunk
+                .iter()
+                .map(|nonce_keypair| create_nonce_account(rpc_client, payer, nonce_keypair, rent)),
+        )
+        .await;
+        let mut unconfirmed = Vec::new();
+        for (nonce_keypair, result) in chunk.iter().zip(results) {
+            match result {
+                Ok(pubkey) => created.push(pubkey),
+                Err(e) => {
+                    tracing::error!("{:#}", e);
+                    unconfirmed.push(nonce_keypair.pubkey().to_string());
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        tracing::info!("Created {}/{} nonce accounts", created.len(), count);
+        if let Some(e) = error.take() {
+            // A creation that was not confirmed, e.g. after a timeout, may still have landed
+            let unconfirmed = unconfirmed.join(", ");
+            tracing::warn!(
+                "Nonce accounts that may have been created and funded anyway: {}",
+                unconfirmed
+            );
+            error = Some(e.context(format!(
+                "Creation of nonce accounts {} was not confirmed; they may be funded",
+                unconfirmed
+            )));
+            break;
+        }
+    }
+    Ok(CreatedNonceAccounts {
+        accounts: created,
+        error,
+    })
+}
+
+async fn create_nonce_account(
+    rpc_client: &RpcClient,
+    payer: &Keypair,
+    nonce_keypair: &Keypair,
+    lamports: u64,
+) -> Result<Pubkey> {
+    let instructions = system_instruction::create_nonce_account(
+        &payer.pubkey(),
+        &nonce_keypair.pubkey(),
+        &payer.pubkey(),
+        lamports,
+    );
+    let blockhash = rpc_client.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[payer, nonce_keypair],
+        blockhash,
+    );
+    rpc_client
+        .send_and_confirm_transaction(&transaction)
+        .await
+        .with_context(|| format!("Failed to create nonce account {}", nonce_keypair.pubkey()))?;
+    tracing::info!("Created nonce account {}", nonce_keypair.pubkey());
+    Ok(nonce_keypair.pubkey())
+}
+
+/// Fetch the current nonce of each account, checking that `authority` may advance it. Accounts
+/// are read `MAX_MULTIPLE_ACCOUNTS` per `getMultipleAccounts` call, one call at a time; also
+/// returns the number of calls made.
+pub async fn fetch_nonces(
+    rpc_client: &RpcClient,
+    accounts: &[Pubkey],
+    authority: &Pubkey,
+) -> Result<(Vec<Hash>, usize)> {
+    let mut nonces = Vec::with_capacity(accounts.len());
+    let mut fetches = 0;
+    for chunk in accounts.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let nonce_accounts = rpc_client
+            .get_multiple_accounts(chunk)
+            .await
+            .with_context(|| format!("Failed to fetch {} nonce accounts", chunk.len()))?;
+        fetches += 1;
+        for (account, nonce_account) in chunk.iter().zip(nonce_accounts) {
+            let nonce_account = nonce_account
+                .ok_or_else(|| anyhow::anyhow!("Nonce account {} does not exist", account))?;
+            let data = data_from_account(&nonce_account)
+                .with_context(|| format!("Invalid nonce account {}", account))?;
+            if data.authority != *authority {
+                anyhow::bail!(
+                    "Nonce account {} is controlled by {}, not the payer {}",
+                    account,
+                    data.authority,
+                    authority
+                );
+            }
+            nonces.push(data.blockhash());
+        }
+    }
+    Ok((nonces, fetches))
+}
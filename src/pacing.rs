@@ -0,0 +1,88 @@
+use crate::config::PacingConfig;
+use anyhow::{Context, Result};
+use rand::Rng;
+use std::time::Duration;
+
+/// Offset from the start of sending at which each of `count` transactions is released. Fails if
+/// an offset does not fit in a `Duration`, e.g. with a vanishingly small `target_tps`.
+pub fn schedule(pacing: &PacingConfig, count: usize) -> Result<Vec<Duration>> {
+    let too_far = || format!("`pacing` {} schedules sends too far out", pacing);
+    match *pacing {
+        PacingConfig::Constant { target_tps } => (0..count)
+            .map(|i| Duration::try_from_secs_f64(i as f64 / target_tps).with_context(too_far))
+            .collect(),
+        PacingConfig::Poisson { target_tps } => {
+            let mut rng = rand::rng();
+            let mut offset = 0.0;
+            (0..count)
+                .map(|_| {
+                    let release = Duration::try_from_secs_f64(offset).with_context(too_far)?;
+                    // Inverse transform sampling of the exponential gap; 1 - u is in (0, 1]
+                    let u: f64 = rng.random();
+                    offset += -(1.0 - u).ln() / target_tps;
+                    Ok(release)
+                })
+                .collect()
+        }
+        PacingConfig::Burst { size, interval_ms } => (0..count)
+            .map(|i| {
+                ((i / size) as u64)
+                    .checked_mul(interval_ms)
+                    .map(Duration::from_millis)
+                    .with_context(too_far)
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_pacing_spaces_sends_evenly() {
+        let offsets = schedule(&PacingConfig::Constant { target_tps: 4.0 }, 5).unwrap();
+        assert_eq!(
+            offsets,
+            [0, 250, 500, 750, 1_000].map(Duration::from_millis)
+        );
+    }
+
+    #[test]
+    fn burst_pacing_groups_sends_by_size() {
+        let offsets = schedule(
+            &PacingConfig::Burst {
+                size: 3,
+                interval_ms: 100,
+            },
+            7,
+        )
+        .unwrap();
+        assert_eq!(
+            offsets,
+            [0, 0, 0, 100, 100, 100, 200].map(Duration::from_millis)
+        );
+    }
+
+    #[test]
+    fn poisson_pacing_starts_at_zero_and_never_goes_back() {
+        let offsets = schedule(&PacingConfig::Poisson { target_tps: 100.0 }, 1_000).unwrap();
+        assert_eq!(offsets.len(), 1_000);
+        assert_eq!(offsets[0], Duration::ZERO);
+        assert!(offsets.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn pacing_too_slow_to_schedule_is_an_error() {
+        assert!(schedule(&PacingConfig::Constant { target_tps: 1e-300 }, 2).is_err());
+        assert!(schedule(&PacingConfig::Poisson { target_tps: 1e-300 }, 2).is_err());
+        assert!(schedule(
+            &PacingConfig::Burst {
+                size: 1,
+                interval_ms: u64::MAX,
+            },
+            3,
+        )
+        .is_err());
+    }
+}
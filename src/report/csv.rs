@@ -0,0 +1,255 @@
+use super::{LatencyStats, Report};
+use crate::rpc::SendErrorKind;
+
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn latency_stats_fields(stats: Option<&LatencyStats>) -> [String; 8] {
+    match stats {
+        Some(stats) => [
+            stats.count.to_string(),
+            stats.p50.to_string(),
+            stats.p90.to_string(),
+            stats.p95.to_string(),
+            stats.p99.to_string(),
+            stats.max.to_string(),
+            format!("{:.3}", stats.mean),
+            format!("{:.3}", stats.std_dev),
+        ],
+        None => Default::default(),
+    }
+}
+
+fn latency_stats_header(prefix: &str) -> String {
+    [
+        "count",
+        "p50_us",
+        "p90_us",
+        "p95_us",
+        "p99_us",
+        "max_us",
+        "mean_us",
+        "std_dev_us",
+    ]
+    .iter()
+    .map(|column| format!("{}_{}", prefix, column))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// One row per (signature, node, commitment) with raw timestamps on the `clock` timeline.
+pub fn render_observations(report: &Report) -> String {
+    let mut csv =
+        String::from("signature,node,commitment,send_start_us,send_ack_us,send_position,send_error,confirmed_us,delta_us,unconfirmed_reason,resubscribed,slot,err,compute_unit_price,payer\n");
+    for signature in &report.signatures {
+        for observation in &signature.nodes {
+            for confirmation in &observation.commitments {
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                    escape(&signature.signature),
+                    escape(&observation.node),
+                    confirmation.commitment,
+                    optional(observation.send_start_us),
+                    optional(observation.send_ack_us),
+                    optional(observation.send_position),
+                    optional(observation.send_error),
+                    optional(confirmation.confirmed_us),
+                    optional(confirmation.delta_us),
+                    escape(&optional(confirmation.unconfirmed_reason.as_ref())),
+                    confirmation.resubscribed,
+                    optional(confirmation.slot),
+                    escape(&optional(confirmation.err.as_ref())),
+                    optional(signature.compute_unit_price),
+                    escape(&optional(signature.payer.as_ref()))
+                ));
+            }
+        }
+    }
+    csv
+}
+
+/// One row per node, in ranking order (excluded and send-only nodes have empty `rank` and
+/// `score_us`).
+pub fn render_summary(report: &Report) -> String {
+    let mut csv = format!(
+        "rank,node,role,confirmed,failed,missed,timed_out,success_rate,sum_delta_us,penalty_us,score_us,disconnects,reconnect_gap_us,reconnect_failed,resubscribed,send_rate_tps,sent_first,sends,sends_per_landed_mean,sends_per_landed_max,skip_preflight,preflight_commitment,max_retries,encoding,min_context_slot,{},{},{},{}\n",
+        SendErrorKind::ALL
+            .iter()
+            .map(|kind| format!("send_errors_{}", kind))
+            .collect::<Vec<_>>()
+            .join(","),
+        latency_stats_header("delta"),
+        latency_stats_header("send_to_ack"),
+        latency_stats_header("send_to_confirmation")
+    );
+    for summary in &report.summary {
+        let mut fields = vec![
+            optional(summary.rank),
+            escape(&summary.node),
+            summary.role.to_string(),
+            summary.confirmed.to_string(),
+            summary.failed.to_string(),
+            summary.missed.to_string(),
+            summary.timed_out.to_string(),
+            format!("{:.4}", summary.success_rate),
+            summary.sum_delta_us.to_string(),
+            summary.penalty_us.to_string(),
+            optional(summary.score_us),
+            summary.disconnects.to_string(),
+            summary.reconnect_gap_us.to_string(),
+            summary.reconnect_failed.to_string(),
+            summary.resubscribed.to_string(),
+            optional(summary.send_rate_tps.map(|tps| format!("{:.3}", tps))),
+            summary.sent_first.to_string(),
+            summary.sends.to_string(),
+            optional(
+                summary
+                    .sends_per_landed
+                    .as_ref()
+                    .map(|stats| format!("{:.3}", stats.mean)),
+            ),
+            optional(summary.sends_per_landed.as_ref().map(|stats| stats.max)),
+        ];
+        let options = summary.send_options.unwrap_or_default();
+        fields.extend([
+            optional(options.skip_preflight),
+            optional(options.preflight_commitment),
+            optional(options.max_retries),
+            optional(options.encoding),
+            optional(options.min_context_slot),
+        ]);
+        fields.extend(SendErrorKind::ALL.iter().map(|kind| {
+            summary
+                .send_errors
+                .get(kind)
+                .copied()
+                .unwrap_or_default()
+                .to_string()
+        }));
+        fields.extend(latency_stats_fields(summary.delta.as_ref()));
+        fields.extend(latency_stats_fields(summary.send_to_ack.as_ref()));
+        fields.extend(latency_stats_fields(summary.send_to_confirmation.as_ref()));
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Commitment, MissingPenalty};
+    use crate::report::RunData;
+    use crate::rpc::SendRecord;
+    use crate::websocket::{ConfirmationResult, MonitorResult};
+
+    /// Number of fields in a CSV line, skipping commas inside quoted fields.
+    fn field_count(line: &str) -> usize {
+        let mut quoted = false;
+        1 + line
+            .chars()
+            .filter(|&c| {
+                if c == '"' {
+                    quoted = !quoted;
+                }
+                c == ',' && !quoted
+            })
+            .count()
+    }
+
+    /// Two signatures sent to and confirmed by a node whose name needs quoting, at two
+    /// commitments.
+    fn report() -> Report {
+        let node = r#"a,"b""#;
+        let confirmation = |signature: &str, commitment, timestamp_us| ConfirmationResult {
+            signature: signature.to_string(),
+            commitment,
+            timestamp_us,
+            resubscribed: false,
+            slot: 7,
+            err: Some("custom, \"1\"".to_string()),
+        };
+        let run = RunData {
+            expected_signatures: vec!["s1".to_string(), "s2".to_string()],
+            send_records: ["s1", "s2"]
+                .map(|signature| SendRecord {
+                    signature: signature.to_string(),
+                    node_name: node.to_string(),
+                    send_start_us: 0,
+                    send_ack_us: 50,
+                    send_position: Some(0),
+                    attempt: 0,
+                    error: None,
+                })
+                .to_vec(),
+            node_results: vec![(
+                node.to_string(),
+                MonitorResult {
+                    confirmations: vec![
+                        confirmation("s1", Commitment::Processed, 100),
+                        confirmation("s1", Commitment::Confirmed, 200),
+                        confirmation("s2", Commitment::Processed, 300),
+                    ],
+                    ..MonitorResult::default()
+                },
+            )],
+            ..RunData::default()
+        };
+        Report::build(
+            &run,
+            &MissingPenalty::MaxObserved,
+            &[Commitment::Processed, Commitment::Confirmed],
+        )
+    }
+
+    #[test]
+    fn every_row_has_as_many_fields_as_the_header() {
+        let report = report();
+        for csv in [render_observations(&report), render_summary(&report)] {
+            let mut lines = csv.lines();
+            let columns = field_count(lines.next().unwrap());
+            let rows: Vec<_> = lines.collect();
+            assert!(!rows.is_empty());
+            for row in rows {
+                assert_eq!(field_count(row), columns, "{}", row);
+            }
+        }
+        // One row per signature and commitment
+        assert_eq!(render_observations(&report).lines().count(), 5);
+    }
+
+    #[test]
+    fn fields_with_commas_and_quotes_are_quoted() {
+        let report = report();
+        let observations = render_observations(&report);
+        let row = observations.lines().nth(1).unwrap();
+        assert!(row.starts_with(r#"s1,"a,""b""",processed,"#));
+        assert!(row.contains(r#","custom, ""1""","#));
+        let summary = render_summary(&report);
+        assert!(summary
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with(r#"1,"a,""b""",both,"#));
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn an_empty_run_renders_only_the_headers() {
+        let report = Report::build(&RunData::default(), &MissingPenalty::MaxObserved, &[]);
+        for csv in [render_observations(&report), render_summary(&report)] {
+            assert_eq!(csv.lines().count(), 1);
+            assert!(csv.ends_with('\n'));
+        }
+    }
+}
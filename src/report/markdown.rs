@@ -0,0 +1,690 @@
+use super::{LandingStats, LatencyStats, Report, HISTOGRAM_BUCKETS_US};
+use crate::rpc::SendErrorKind;
+
+const LATENCY_STATS_HEADER: &str = "| Count | p50 | p90 | p95 | p99 | Max | Mean | Std Dev ";
+const LATENCY_STATS_SEPARATOR: &str = "|---|---|---|---|---|---|---|---";
+
+/// `text` safe to put in a table cell: a `|` would end the cell early.
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+fn format_duration_us(us: u64) -> String {
+    match us {
+        0 => "0 μs".to_string(),
+        us if us < 1_000 => format!("{} μs", us),
+        us if us < 1_000_000 => format!("{:.2} ms", us as f64 / 1_000.0),
+        _ => format!("{:.2} s", us as f64 / 1_000_000.0),
+    }
+}
+
+fn format_success_rate(confirmed: usize, total: usize) -> String {
+    if total == 0 {
+        return "N/A".to_string();
+    }
+    format!("{:.2}%", confirmed as f64 * 100.0 / total as f64)
+}
+
+/// Markdown cells `| count | p50 | ... | std dev ` matching `LATENCY_STATS_HEADER`.
+fn latency_stats_cells(stats: &LatencyStats) -> String {
+    format!(
+        "| {} | {} | {} | {} | {} | {} | {} | {} ",
+        stats.count,
+        format_duration_us(stats.p50),
+        format_duration_us(stats.p90),
+        format_duration_us(stats.p95),
+        format_duration_us(stats.p99),
+        format_duration_us(stats.max),
+        format_duration_us(stats.mean.round() as u64),
+        format_duration_us(stats.std_dev.round() as u64)
+    )
+}
+
+fn histogram_bucket_label(index: usize) -> String {
+    match index {
+        0 => "0".to_string(),
+        i if i < HISTOGRAM_BUCKETS_US.len() => format!(
+            "{} – {}",
+            format_duration_us(HISTOGRAM_BUCKETS_US[i - 1]),
+            format_duration_us(HISTOGRAM_BUCKETS_US[i])
+        ),
+        _ => format!(
+            "≥ {}",
+            format_duration_us(HISTOGRAM_BUCKETS_US[HISTOGRAM_BUCKETS_US.len() - 1])
+        ),
+    }
+}
+
+/// Markdown cells `| transactions | landed | failed | <latency stats> `.
+fn landing_stats_cells(stats: &LandingStats) -> String {
+    let latency = match &stats.landing_latency {
+        Some(latency) => latency_stats_cells(latency),
+        None => "| N/A ".repeat(8),
+    };
+    format!(
+        "| {} | {} | {} {}",
+        stats.transactions, stats.landed, stats.failed, latency
+    )
+}
+
+/// Generate a markdown report comparing confirmation times per signature across nodes.
+pub fn render(report: &Report) -> String {
+    let mut md = String::new();
+
+    // Per-signature delta table
+    let primary_commitment = report.commitments[0];
+    md.push_str("## Per-Signature Δ from Fastest\n\n");
+    md.push_str(&format!("This report shows, for each signature, how much slower each node was compared to the fastest node (Δ) at `{}` commitment.\n", primary_commitment));
+    md.push_str("'N/A' means no confirmation from that node. '*' marks a confirmation received after a WebSocket reconnect, which may have been delayed by the disconnect.\n\n");
+    md.push_str("Nodes are ranked by their total Δ plus the penalty for missed signatures (lower is better) in the summary below.\n\n");
+    md.push_str(&format!(
+        "{} signatures: {} succeeded, {} failed, {} did not land.\n\n",
+        report.total_signatures,
+        report.succeeded_signatures,
+        report.failed_signatures,
+        report.total_signatures - report.succeeded_signatures - report.failed_signatures
+    ));
+    let watched_nodes: Vec<_> = report
+        .nodes
+        .iter()
+        .filter(|node| report.watches(node))
+        .collect();
+    md.push_str("| Signature | Status | Landing Slot ");
+    for node in &watched_nodes {
+        md.push_str(&format!("| {} (Δ) ", escape(node)));
+    }
+    md.push_str("|\n");
+    md.push_str("|---|---|---");
+    for _ in &watched_nodes {
+        md.push_str("|---");
+    }
+    md.push_str("|\n");
+    for signature in &report.signatures {
+        md.push_str(&format!(
+            "| {} | {} | {} ",
+            signature.signature,
+            signature.status,
+            signature
+                .landing_slot
+                .map_or("N/A".to_string(), |slot| slot.to_string())
+        ));
+        for observation in &signature.nodes {
+            if !report.watches(&observation.node) {
+                continue;
+            }
+            let primary = observation.primary();
+            match primary.delta_us {
+                Some(delta) => md.push_str(&format!(
+                    "| {}{} ",
+                    format_duration_us(delta),
+                    if primary.resubscribed { " *" } else { "" }
+                )),
+                None => md.push_str("| N/A "),
+            }
+        }
+        md.push_str("|\n");
+    }
+
+    // Node score table
+    md.push_str("\n## Node Performance Summary (Lower Score is Better)\n\n");
+    md.push_str(&format!(
+        "Missed signatures are penalized using the `{}` model. Score = Sum Δ + Penalty.\n\n",
+        report.missing_penalty
+    ));
+    md.push_str(
+        "| Order | Node Name | Confirmed | Failed | Success Rate | Missed | Timed Out | Sum Δ | Penalty | Score |
+|---|---|---|---|---|---|---|---|---|---|
+",
+    );
+    for summary in report
+        .summary
+        .iter()
+        .filter(|summary| summary.role.watches())
+    {
+        let (order, penalty, score) = match (summary.rank, summary.score_us) {
+            (Some(rank), Some(score)) => (
+                rank.to_string(),
+                format_duration_us(summary.penalty_us),
+                format_duration_us(score),
+            ),
+            _ => ("excluded".to_string(), "N/A".to_string(), "N/A".to_string()),
+        };
+        md.push_str(&format!(
+            "| {} | {} | {}/{} | {} | {} | {} | {} | {} | {} | {} |\n",
+            order,
+            escape(&summary.node),
+            summary.confirmed,
+            report.total_signatures,
+            summary.failed,
+            format_success_rate(summary.confirmed, report.total_signatures),
+            summary.missed,
+            summary.timed_out,
+            format_duration_us(summary.sum_delta_us),
+            penalty,
+            score
+        ));
+    }
+
+    // Δ distribution table
+    md.push_str("\n## Node Δ Distribution\n\n");
+    md.push_str(&format!("| Node Name {}|\n", LATENCY_STATS_HEADER));
+    md.push_str(&format!("|---{}|\n", LATENCY_STATS_SEPARATOR));
+    for summary in &report.summary {
+        if let Some(stats) = &summary.delta {
+            md.push_str(&format!(
+                "| {} {}|\n",
+                escape(&summary.node),
+                latency_stats_cells(stats)
+            ));
+        }
+    }
+
+    // Δ histogram table
+    let histogram_nodes: Vec<_> = report
+        .summary
+        .iter()
+        .filter(|summary| summary.delta.is_some())
+        .collect();
+    md.push_str("\n## Node Δ Histogram\n\n");
+    md.push_str("| Δ Bucket ");
+    for summary in &histogram_nodes {
+        md.push_str(&format!("| {} ", escape(&summary.node)));
+    }
+    md.push_str("|\n");
+    md.push_str("|---");
+    for _ in &histogram_nodes {
+        md.push_str("|---");
+    }
+    md.push_str("|\n");
+    for bucket in 0..=HISTOGRAM_BUCKETS_US.len() {
+        md.push_str(&format!("| {} ", histogram_bucket_label(bucket)));
+        for summary in &histogram_nodes {
+            md.push_str(&format!("| {} ", summary.delta_histogram[bucket]));
+        }
+        md.push_str("|\n");
+    }
+
+    // Landing latency per fee tier
+    if !report.fee_tiers.is_empty() {
+        md.push_str("\n## Landing Latency per Fee Tier\n\n");
+        md.push_str(&format!("Landing latency is the time from the first send to any node until the first `{}` notification from any node.\n\n", primary_commitment));
+        md.push_str(&format!(
+            "| CU Price (μlamports) | Transactions | Landed | Failed {}|\n",
+            LATENCY_STATS_HEADER
+        ));
+        md.push_str(&format!("|---|---|---|---{}|\n", LATENCY_STATS_SEPARATOR));
+        for tier in &report.fee_tiers {
+            md.push_str(&format!(
+                "| {} {}|\n",
+                tier.compute_unit_price
+                    .map_or("none".to_string(), |price| price.to_string()),
+                landing_stats_cells(&tier.stats)
+            ));
+        }
+    }
+
+    // Landing latency per payer
+    if !report.payers.is_empty() {
+        md.push_str("\n## Landing Latency per Payer\n\n");
+        md.push_str(&format!(
+            "| Payer | Transactions | Landed | Failed {}|\n",
+            LATENCY_STATS_HEADER
+        ));
+        md.push_str(&format!("|---|---|---|---{}|\n", LATENCY_STATS_SEPARATOR));
+        for payer in &report.payers {
+            md.push_str(&format!(
+                "| {} {}|\n",
+                payer.payer,
+                landing_stats_cells(&payer.stats)
+            ));
+        }
+    }
+
+    // Sender × observer latency in propagation mode
+    if let Some(propagation) = &report.propagation {
+        md.push_str("\n## Propagation Matrix\n\n");
+        md.push_str(&format!("Each transaction was sent to a single node ({}). Cells show the median time from sending to the sender (row) until the observer (column) delivered the `{}` notification, with the sample count: a row shows how fast a provider forwards to the leader, a column how fast a provider observes state.\n\n", propagation, primary_commitment));
+        md.push_str("| Sender ╲ Observer | Transactions ");
+        for node in &watched_nodes {
+            md.push_str(&format!("| {} ", escape(node)));
+        }
+        md.push_str("|\n|---|---");
+        md.push_str(&"|---".repeat(watched_nodes.len()));
+        md.push_str("|\n");
+        for row in &report.propagation_matrix {
+            md.push_str(&format!(
+                "| {} | {} ",
+                escape(&row.sender),
+                row.transactions
+            ));
+            for cell in &row.observers {
+                match &cell.latency {
+                    Some(stats) => md.push_str(&format!(
+                        "| {} ({}) ",
+                        format_duration_us(stats.p50),
+                        stats.count
+                    )),
+                    None => md.push_str("| N/A "),
+                }
+            }
+            md.push_str("|\n");
+        }
+    }
+
+    // Transaction build throughput
+    if let Some(build) = &report.build {
+        md.push_str("\n## Transaction Build\n\n");
+        let sources = if build.nonce_fetches > 0 {
+            format!(
+                "{} `getMultipleAccounts` call(s) reading durable nonces",
+                build.nonce_fetches
+            )
+        } else {
+            format!("{} blockhash fetch(es)", build.blockhash_fetches)
+        };
+        md.push_str(&format!(
+            "Built {} transactions in {} ({:.0} tx/s) using {}.\n",
+            build.transactions,
+            format_duration_us(build.elapsed_us),
+            build.transactions_per_second,
+            sources
+        ));
+        if report.index_memo {
+            md.push_str(
+                "\nEvery transaction also carried a memo with its index to keep signatures \
+                 distinct, adding an instruction and its compute units to the configured program \
+                 instruction.\n",
+            );
+        }
+    }
+
+    // Achieved send rate against the pacing target
+    md.push_str("\n## Send Rate\n\n");
+    match &report.pacing {
+        Some(pacing) => md.push_str(&format!(
+            "Sends were paced: {}, targeting {:.1} tx/s per node.\n\n",
+            pacing,
+            pacing.target_tps()
+        )),
+        None => md.push_str(
+            "Sends were not paced; each node was sent to as fast as it accepted transactions.\n\n",
+        ),
+    }
+    md.push_str("| Node Name | Achieved Rate |\n|---|---|\n");
+    let senders: Vec<_> = report
+        .summary
+        .iter()
+        .filter(|summary| summary.role.sends())
+        .collect();
+    for summary in &senders {
+        md.push_str(&format!(
+            "| {} | {} |\n",
+            escape(&summary.node),
+            summary
+                .send_rate_tps
+                .map_or("N/A".to_string(), |tps| format!("{:.1} tx/s", tps))
+        ));
+    }
+
+    // Δ by send position, to control for which node was sent each signature first
+    let positions = report
+        .summary
+        .iter()
+        .map(|summary| summary.delta_by_send_position.len())
+        .max()
+        .unwrap_or(0);
+    if senders.len() > 1 && positions > 0 && report.propagation.is_none() {
+        md.push_str("\n## Send Order\n\n");
+        md.push_str(&format!(
+            "Fairness: {}. Median Δ (and sample count) by the node's position in each signature's send order, 1 being the first node sent to. Compare nodes at the same position to rule out send order bias.\n\n",
+            report.fairness
+        ));
+        md.push_str("| Node Name | Sent First ");
+        for position in 1..=positions {
+            md.push_str(&format!("| Position {} ", position));
+        }
+        md.push_str("|\n|---|---");
+        md.push_str(&"|---".repeat(positions));
+        md.push_str("|\n");
+        for summary in &senders {
+            md.push_str(&format!(
+                "| {} | {} ",
+                escape(&summary.node),
+                summary.sent_first
+            ));
+            for position in 0..positions {
+                match summary.delta_by_send_position.get(position) {
+                    Some(Some(stats)) => md.push_str(&format!(
+                        "| {} ({}) ",
+                        format_duration_us(stats.p50),
+                        stats.count
+                    )),
+                    _ => md.push_str("| N/A "),
+                }
+            }
+            md.push_str("|\n");
+        }
+    }
+
+    // Sends per landed transaction when rebroadcasting
+    if let Some(rebroadcast) = &report.rebroadcast {
+        md.push_str("\n## Rebroadcast\n\n");
+        md.push_str(&format!("Transactions were rebroadcast {}. Sends per landed transaction count the sends to a node started before the first notification from any node.\n\n", rebroadcast));
+        md.push_str("| Node Name | Sends | Landed | Mean Sends per Landed | p50 | p90 | Max |\n|---|---|---|---|---|---|---|\n");
+        for summary in &senders {
+            match &summary.sends_per_landed {
+                Some(stats) => md.push_str(&format!(
+                    "| {} | {} | {} | {:.2} | {} | {} | {} |\n",
+                    escape(&summary.node),
+                    summary.sends,
+                    stats.landed,
+                    stats.mean,
+                    stats.p50,
+                    stats.p90,
+                    stats.max
+                )),
+                None => md.push_str(&format!(
+                    "| {} | {} | 0 | N/A | N/A | N/A | N/A |\n",
+                    escape(&summary.node),
+                    summary.sends
+                )),
+            }
+        }
+    }
+
+    // sendTransaction options, which change how providers handle a transaction
+    if report
+        .summary
+        .iter()
+        .any(|summary| summary.send_options.is_some())
+    {
+        md.push_str("\n## Send Options\n\n");
+        md.push_str("'default' means the option was left to the RPC client or node.\n\n");
+        md.push_str("| Node Name | Skip Preflight | Preflight Commitment | Max Retries | Encoding | Min Context Slot |\n|---|---|---|---|---|---|\n");
+        let cell = |value: Option<String>| value.unwrap_or_else(|| "default".to_string());
+        for summary in &report.summary {
+            let Some(options) = summary.send_options else {
+                continue;
+            };
+            md.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} |\n",
+                escape(&summary.node),
+                cell(options.skip_preflight.map(|skip| skip.to_string())),
+                cell(options.preflight_commitment.map(|c| c.to_string())),
+                cell(options.max_retries.map(|retries| retries.to_string())),
+                cell(options.encoding.map(|encoding| encoding.to_string())),
+                cell(options.min_context_slot.map(|slot| slot.to_string()))
+            ));
+        }
+    }
+
+    // Absolute latency table
+    md.push_str("\n## Absolute Latency\n\n");
+    md.push_str(&format!("Send → Ack is the `sendTransaction` HTTP round trip to a node; Send → Confirmation is the time from sending to that node until the same node delivered the `{}` notification.\n\n", primary_commitment));
+    md.push_str(&format!("| Node Name | Metric {}|\n", LATENCY_STATS_HEADER));
+    md.push_str(&format!("|---|---{}|\n", LATENCY_STATS_SEPARATOR));
+    for summary in &report.summary {
+        for (metric, stats) in [
+            ("Send → Ack", &summary.send_to_ack),
+            ("Send → Confirmation", &summary.send_to_confirmation),
+        ] {
+            if let Some(stats) = stats {
+                md.push_str(&format!(
+                    "| {} | {} {}|\n",
+                    escape(&summary.node),
+                    metric,
+                    latency_stats_cells(stats)
+                ));
+            }
+        }
+    }
+
+    // Failed transactions
+    if report.failed_signatures > 0 {
+        md.push_str("\n## Failed Transactions\n\n");
+        md.push_str(
+            "These transactions landed but failed; they are still counted as confirmed above.\n\n",
+        );
+        md.push_str("| Signature | Landing Slot | Error |\n|---|---|---|\n");
+        for signature in &report.signatures {
+            if let (Some(slot), Some(err)) = (signature.landing_slot, &signature.err) {
+                md.push_str(&format!(
+                    "| {} | {} | {} |\n",
+                    signature.signature,
+                    slot,
+                    escape(err)
+                ));
+            }
+        }
+    }
+
+    // Failed sends by error kind, only for kinds that occurred
+    let error_kinds: Vec<SendErrorKind> = SendErrorKind::ALL
+        .into_iter()
+        .filter(|kind| {
+            report
+                .summary
+                .iter()
+                .any(|summary| summary.send_errors.contains_key(kind))
+        })
+        .collect();
+    if !error_kinds.is_empty() {
+        md.push_str("\n## Send Errors\n\n");
+        md.push_str("Failed `sendTransaction` calls per node. The run continued past them; a transaction may still land through another node.\n\n");
+        md.push_str("| Node Name ");
+        for kind in &error_kinds {
+            md.push_str(&format!("| {} ", kind));
+        }
+        md.push_str("| Total |\n|---");
+        md.push_str(&"|---".repeat(error_kinds.len()));
+        md.push_str("|---|\n");
+        for summary in &senders {
+            md.push_str(&format!("| {} ", escape(&summary.node)));
+            for kind in &error_kinds {
+                md.push_str(&format!(
+                    "| {} ",
+                    summary.send_errors.get(kind).copied().unwrap_or_default()
+                ));
+            }
+            md.push_str(&format!(
+                "| {} |\n",
+                summary.send_errors.values().sum::<usize>()
+            ));
+        }
+    }
+
+    // Unconfirmed signatures by reason
+    if !report.unconfirmed.is_empty() {
+        md.push_str("\n## Unconfirmed Signatures\n\n");
+        md.push_str("| Node Name | Commitment | Reason | Count |\n|---|---|---|---|\n");
+        for entry in &report.unconfirmed {
+            md.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                escape(&entry.node),
+                entry.commitment,
+                entry.reason,
+                entry.count
+            ));
+        }
+    }
+
+    // WebSocket reconnects, flagging nodes whose results may be affected
+    let reconnected_nodes: Vec<_> = report
+        .summary
+        .iter()
+        .filter(|summary| summary.affected_by_reconnects())
+        .collect();
+    if !reconnected_nodes.is_empty() {
+        md.push_str("\n## WebSocket Reconnects\n\n");
+        md.push_str("Results of these nodes may be affected: notifications can be delayed or lost while disconnected.\n\n");
+        md.push_str("| Node Name | Disconnects | Total Gap | Gave Up | Resubscribed Confirmations |\n|---|---|---|---|---|\n");
+        for summary in reconnected_nodes {
+            md.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                escape(&summary.node),
+                summary.disconnects,
+                format_duration_us(summary.reconnect_gap_us),
+                if summary.reconnect_failed {
+                    "yes"
+                } else {
+                    "no"
+                },
+                summary.resubscribed
+            ));
+        }
+    }
+
+    // Per-commitment tables, only interesting when more than one level is tracked
+    if report.commitment_levels.len() > 1 {
+        md.push_str("\n## Δ per Commitment Level\n\n");
+        md.push_str(&format!(
+            "| Commitment | Node Name | Confirmed | Metric {}|\n",
+            LATENCY_STATS_HEADER
+        ));
+        md.push_str(&format!("|---|---|---|---{}|\n", LATENCY_STATS_SEPARATOR));
+        for level in &report.commitment_levels {
+            for node in &level.nodes {
+                for (metric, stats) in [
+                    ("Δ", &node.delta),
+                    ("Send → Confirmation", &node.send_to_confirmation),
+                ] {
+                    if let Some(stats) = stats {
+                        md.push_str(&format!(
+                            "| {} | {} | {}/{} | {} {}|\n",
+                            level.commitment,
+                            escape(&node.node),
+                            node.confirmed,
+                            report.total_signatures,
+                            metric,
+                            latency_stats_cells(stats)
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Commitment, MissingPenalty};
+    use crate::report::RunData;
+    use crate::rpc::SendRecord;
+    use crate::websocket::{ConfirmationResult, MonitorResult};
+    use std::collections::BTreeMap;
+
+    /// Number of cell separators in a table row, skipping escaped ones.
+    fn separator_count(row: &str) -> usize {
+        row.matches('|').count() - row.matches("\\|").count()
+    }
+
+    /// Lines of each table in `md`, i.e. of each run of lines starting with `|`.
+    fn tables(md: &str) -> Vec<Vec<&str>> {
+        let mut tables: Vec<Vec<&str>> = Vec::new();
+        let mut in_table = false;
+        for line in md.lines() {
+            if line.starts_with('|') {
+                if !in_table {
+                    tables.push(Vec::new());
+                }
+                tables.last_mut().unwrap().push(line);
+            }
+            in_table = line.starts_with('|');
+        }
+        tables
+    }
+
+    /// `x|y` confirms both signatures and `z` only `s1`, after failing to send `s2` once. `s1`
+    /// sets a priority fee and the signatures have different payers.
+    fn run() -> RunData {
+        let confirmation = |signature: &str, timestamp_us| ConfirmationResult {
+            signature: signature.to_string(),
+            commitment: Commitment::Processed,
+            timestamp_us,
+            resubscribed: false,
+            slot: 7,
+            err: (signature == "s2").then(|| "custom | 1".to_string()),
+        };
+        let send = |signature: &str, node: &str, error| SendRecord {
+            signature: signature.to_string(),
+            node_name: node.to_string(),
+            send_start_us: 0,
+            send_ack_us: 50,
+            send_position: Some(0),
+            attempt: 0,
+            error,
+        };
+        RunData {
+            expected_signatures: vec!["s1".to_string(), "s2".to_string()],
+            compute_unit_prices: BTreeMap::from([("s1".to_string(), 1_000)]),
+            payers: BTreeMap::from([
+                ("s1".to_string(), "p1".to_string()),
+                ("s2".to_string(), "p2".to_string()),
+            ]),
+            send_records: vec![
+                send("s1", "x|y", None),
+                send("s2", "x|y", None),
+                send("s1", "z", None),
+                send("s2", "z", Some(SendErrorKind::RateLimited)),
+            ],
+            node_results: vec![
+                (
+                    "x|y".to_string(),
+                    MonitorResult {
+                        confirmations: vec![confirmation("s1", 100), confirmation("s2", 300)],
+                        ..MonitorResult::default()
+                    },
+                ),
+                (
+                    "z".to_string(),
+                    MonitorResult {
+                        confirmations: vec![confirmation("s1", 150)],
+                        ..MonitorResult::default()
+                    },
+                ),
+            ],
+            ..RunData::default()
+        }
+    }
+
+    #[test]
+    fn every_table_row_has_as_many_cells_as_its_header() {
+        let md = render(&Report::build(&run(), &MissingPenalty::MaxObserved, &[]));
+        let tables = tables(&md);
+        assert!(tables.len() >= 10, "{}", md);
+        for table in tables {
+            let columns = separator_count(table[0]);
+            for row in &table[1..] {
+                assert_eq!(separator_count(row), columns, "{}", row);
+            }
+        }
+    }
+
+    #[test]
+    fn pipes_in_cells_are_escaped() {
+        let md = render(&Report::build(&run(), &MissingPenalty::MaxObserved, &[]));
+        assert!(md.contains("| x\\|y (Δ) "));
+        assert!(md.contains("| 1 | x\\|y | 2/2 "));
+        assert!(md.contains("| custom \\| 1 |"));
+        assert!(!md.contains(" x|y"));
+    }
+
+    #[test]
+    fn an_empty_run_renders_empty_tables() {
+        let md = render(&Report::build(
+            &RunData::default(),
+            &MissingPenalty::MaxObserved,
+            &[],
+        ));
+        assert!(md.contains("0 signatures: 0 succeeded, 0 failed, 0 did not land."));
+        assert!(!md.contains("## Send Errors"));
+        assert!(!md.contains("## Failed Transactions"));
+        for table in tables(&md) {
+            let columns = separator_count(table[0]);
+            assert!(table.iter().all(|row| separator_count(row) == columns));
+        }
+    }
+}
@@ -0,0 +1,1397 @@
+mod csv;
+mod markdown;
+
+use crate::config::{
+    Commitment, FairnessConfig, MissingPenalty, NodeRole, OutputFormat, PacingConfig,
+    PropagationConfig, RebroadcastConfig, SendOptions,
+};
+use crate::rpc::{SendErrorKind, SendRecord};
+use crate::transaction::BuildStats;
+use crate::websocket::{ConfirmationResult, MonitorResult, UnconfirmedReason};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+pub type NodeName = String;
+
+/// Everything a run produced, as consumed by `Report::build`.
+#[derive(Debug, Default)]
+pub struct RunData {
+    /// All signatures that were sent.
+    pub expected_signatures: Vec<String>,
+    /// Priority fee (micro-lamports per CU) of each signature that set one.
+    pub compute_unit_prices: BTreeMap<String, u64>,
+    /// Payer of each signature.
+    pub payers: BTreeMap<String, String>,
+    pub build: Option<BuildStats>,
+    /// Send schedule, if sends were paced.
+    pub pacing: Option<PacingConfig>,
+    pub fairness: FairnessConfig,
+    /// Effective `sendTransaction` options per node; empty for event logs recorded before they
+    /// were logged.
+    pub send_options: BTreeMap<NodeName, SendOptions>,
+    pub rebroadcast: Option<RebroadcastConfig>,
+    pub propagation: Option<PropagationConfig>,
+    /// Whether each transaction carried an extra memo with its index.
+    pub index_memo: bool,
+    /// Role of each node; nodes missing from it were both sent to and watched.
+    pub roles: BTreeMap<NodeName, NodeRole>,
+    pub send_records: Vec<SendRecord>,
+    /// Per-node monitoring results of the watched nodes; a node whose monitor failed has every
+    /// signature unconfirmed.
+    pub node_results: Vec<(NodeName, MonitorResult)>,
+}
+
+/// Upper bounds (exclusive, in μs) of the Δ histogram buckets. Anything above the last bound
+/// falls into a final open-ended bucket.
+pub const HISTOGRAM_BUCKETS_US: [u64; 8] =
+    [1, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000];
+
+/// Distribution summary of a set of latency samples, in μs.
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub p50: u64,
+    pub p90: u64,
+    pub p95: u64,
+    pub p99: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl LatencyStats {
+    fn from_samples(samples: &[u64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+
+        let count = sorted.len();
+        let mean = sorted.iter().map(|&d| d as f64).sum::<f64>() / count as f64;
+        let variance = sorted
+            .iter()
+            .map(|&d| (d as f64 - mean).powi(2))
+            .sum::<f64>()
+            / count as f64;
+
+        Some(Self {
+            count,
+            p50: percentile(&sorted, 50.0),
+            p90: percentile(&sorted, 90.0),
+            p95: percentile(&sorted, 95.0),
+            p99: percentile(&sorted, 99.0),
+            max: sorted[count - 1],
+            mean,
+            std_dev: variance.sqrt(),
+        })
+    }
+}
+
+/// How many sends to a node the transactions that landed took.
+#[derive(Debug, Clone, Serialize)]
+pub struct SendCountStats {
+    pub landed: usize,
+    pub mean: f64,
+    pub p50: u64,
+    pub p90: u64,
+    pub max: u64,
+}
+
+impl SendCountStats {
+    fn from_counts(counts: &[u64]) -> Option<Self> {
+        if counts.is_empty() {
+            return None;
+        }
+
+        let mut sorted = counts.to_vec();
+        sorted.sort_unstable();
+        Some(Self {
+            landed: sorted.len(),
+            mean: sorted.iter().sum::<u64>() as f64 / sorted.len() as f64,
+            p50: percentile(&sorted, 50.0),
+            p90: percentile(&sorted, 90.0),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+/// Nearest-rank percentile of an ascending, non-empty slice.
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn histogram_bucket_index(delta_us: u64) -> usize {
+    HISTOGRAM_BUCKETS_US
+        .iter()
+        .position(|&bound| delta_us < bound)
+        .unwrap_or(HISTOGRAM_BUCKETS_US.len())
+}
+
+/// Confirmation of one signature on one node at one commitment level.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitmentObservation {
+    pub commitment: Commitment,
+    pub confirmed_us: Option<u64>,
+    /// Δ from the fastest node at this commitment; `None` if this node never reached it.
+    pub delta_us: Option<u64>,
+    /// Why monitoring gave up on this signature, if it did.
+    pub unconfirmed_reason: Option<UnconfirmedReason>,
+    /// Confirmed on a subscription re-established after a WebSocket reconnect.
+    pub resubscribed: bool,
+    /// Slot reported by this node's notification.
+    pub slot: Option<u64>,
+    /// Transaction error reported by this node's notification.
+    pub err: Option<String>,
+}
+
+/// Raw timings of one signature on one node, on the `clock` timeline.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeObservation {
+    pub node: NodeName,
+    pub send_start_us: Option<u64>,
+    pub send_ack_us: Option<u64>,
+    /// Nodes that started sending this signature before this one.
+    pub send_position: Option<usize>,
+    /// Why sending to this node failed, if it did.
+    pub send_error: Option<SendErrorKind>,
+    /// One entry per tracked commitment, primary commitment first.
+    pub commitments: Vec<CommitmentObservation>,
+}
+
+impl NodeObservation {
+    /// Observation at the primary (ranking) commitment.
+    pub fn primary(&self) -> &CommitmentObservation {
+        &self.commitments[0]
+    }
+}
+
+/// Outcome of a transaction at the primary commitment, as seen by any node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionStatus {
+    Succeeded,
+    Failed,
+    /// No node delivered a notification.
+    NotLanded,
+}
+
+impl std::fmt::Display for TransactionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransactionStatus::Succeeded => write!(f, "succeeded"),
+            TransactionStatus::Failed => write!(f, "failed"),
+            TransactionStatus::NotLanded => write!(f, "not landed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignatureReport {
+    pub signature: String,
+    pub status: TransactionStatus,
+    /// Lowest slot any node reported at the primary commitment.
+    pub landing_slot: Option<u64>,
+    /// Transaction error, if any node reported one at the primary commitment.
+    pub err: Option<String>,
+    /// Priority fee in micro-lamports per compute unit, if the transaction set one.
+    pub compute_unit_price: Option<u64>,
+    pub payer: Option<String>,
+    /// Time from the first send to any node until the first primary-commitment notification
+    /// from any node.
+    pub landing_latency_us: Option<u64>,
+    pub nodes: Vec<NodeObservation>,
+}
+
+/// Per-node summary at the primary commitment, combining speed (Sum Δ), completeness (missed
+/// signatures) and latency distributions.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeSummary {
+    /// 1-based ranking position; `None` if the node was excluded from ranking.
+    pub rank: Option<usize>,
+    pub node: NodeName,
+    /// Nodes that were only sent to are not ranked and have no confirmation statistics.
+    pub role: NodeRole,
+    pub confirmed: usize,
+    /// Confirmed signatures whose transaction failed (included in `confirmed`).
+    pub failed: usize,
+    pub missed: usize,
+    /// Missed signatures that hit the per-signature or overall monitoring timeout.
+    pub timed_out: usize,
+    pub success_rate: f64,
+    pub sum_delta_us: u64,
+    pub penalty_us: u64,
+    /// Sum Δ + penalty; `None` if the node was excluded from ranking.
+    pub score_us: Option<u64>,
+    pub delta: Option<LatencyStats>,
+    /// Count of Δ samples per bucket of `HISTOGRAM_BUCKETS_US` (plus the open-ended bucket).
+    pub delta_histogram: Vec<usize>,
+    pub send_to_ack: Option<LatencyStats>,
+    /// Time from sending to a node until the same node notified the primary commitment.
+    pub send_to_confirmation: Option<LatencyStats>,
+    /// Times the node's WebSocket connection was lost during monitoring.
+    pub disconnects: usize,
+    /// Total time spent disconnected before a successful reconnect.
+    pub reconnect_gap_us: u64,
+    /// Whether the monitor gave up reconnecting after the last disconnect.
+    pub reconnect_failed: bool,
+    /// Confirmations at the primary commitment received on a resubscription.
+    pub resubscribed: usize,
+    /// Sends per second actually achieved, between the node's first and last send.
+    pub send_rate_tps: Option<f64>,
+    /// Signatures this node was the first to be sent.
+    pub sent_first: usize,
+    /// Δ statistics by the node's position in each signature's send order (0 = sent first).
+    pub delta_by_send_position: Vec<Option<LatencyStats>>,
+    /// Failed `sendTransaction` calls by error kind.
+    pub send_errors: BTreeMap<SendErrorKind, usize>,
+    /// `sendTransaction` options the node was sent with, if recorded.
+    pub send_options: Option<SendOptions>,
+    /// `sendTransaction` calls to the node, rebroadcasts included.
+    pub sends: usize,
+    /// Sends to the node each landed transaction took: those started before the first
+    /// notification from any node, at least the first send.
+    pub sends_per_landed: Option<SendCountStats>,
+}
+
+impl NodeSummary {
+    /// Whether reconnects may have affected this node's results.
+    pub fn affected_by_reconnects(&self) -> bool {
+        self.disconnects > 0
+    }
+}
+
+/// Per-node statistics at a single commitment level.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitmentNodeSummary {
+    pub node: NodeName,
+    pub confirmed: usize,
+    pub delta: Option<LatencyStats>,
+    pub send_to_confirmation: Option<LatencyStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitmentSummary {
+    pub commitment: Commitment,
+    pub nodes: Vec<CommitmentNodeSummary>,
+}
+
+/// Landing statistics of a group of transactions.
+#[derive(Debug, Clone, Serialize)]
+pub struct LandingStats {
+    pub transactions: usize,
+    pub landed: usize,
+    pub failed: usize,
+    pub landing_latency: Option<LatencyStats>,
+}
+
+impl LandingStats {
+    fn from_signatures(signatures: &[&SignatureReport]) -> Self {
+        let latencies: Vec<u64> = signatures
+            .iter()
+            .filter_map(|signature| signature.landing_latency_us)
+            .collect();
+        Self {
+            transactions: signatures.len(),
+            landed: signatures
+                .iter()
+                .filter(|signature| signature.status != TransactionStatus::NotLanded)
+                .count(),
+            failed: signatures
+                .iter()
+                .filter(|signature| signature.status == TransactionStatus::Failed)
+                .count(),
+            landing_latency: LatencyStats::from_samples(&latencies),
+        }
+    }
+}
+
+/// Landing statistics of the transactions sharing one compute unit price.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeTierSummary {
+    /// Micro-lamports per compute unit; `None` for transactions without a priority fee.
+    pub compute_unit_price: Option<u64>,
+    #[serde(flatten)]
+    pub stats: LandingStats,
+}
+
+/// Landing statistics of the transactions paid by one payer.
+#[derive(Debug, Clone, Serialize)]
+pub struct PayerSummary {
+    pub payer: String,
+    #[serde(flatten)]
+    pub stats: LandingStats,
+}
+
+/// Time from sending to one node until one observer node was notified.
+#[derive(Debug, Clone, Serialize)]
+pub struct PropagationCell {
+    pub observer: NodeName,
+    pub latency: Option<LatencyStats>,
+}
+
+/// Observed latencies of the transactions sent through one node.
+#[derive(Debug, Clone, Serialize)]
+pub struct PropagationRow {
+    pub sender: NodeName,
+    pub transactions: usize,
+    pub observers: Vec<PropagationCell>,
+}
+
+/// How long building the transaction batch took.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildSummary {
+    pub transactions: usize,
+    pub elapsed_us: u64,
+    pub transactions_per_second: f64,
+    pub blockhash_fetches: usize,
+    /// `getMultipleAccounts` calls reading the nonce accounts of durable nonce transactions.
+    pub nonce_fetches: usize,
+}
+
+/// Structured benchmark result, rendered as markdown, JSON or CSV.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub missing_penalty: MissingPenalty,
+    /// Tracked commitment levels; the first one is used for ranking.
+    pub commitments: Vec<Commitment>,
+    pub total_signatures: usize,
+    /// Absent for event logs recorded before batch building was logged.
+    pub build: Option<BuildSummary>,
+    /// Send schedule; `None` when transactions were sent as fast as nodes accepted them.
+    pub pacing: Option<PacingConfig>,
+    pub fairness: FairnessConfig,
+    pub rebroadcast: Option<RebroadcastConfig>,
+    pub propagation: Option<PropagationConfig>,
+    /// Whether each transaction carried an extra memo with its index, adding an instruction
+    /// and compute units beyond the configured template.
+    pub index_memo: bool,
+    /// Sender × observer latency at the primary commitment; empty unless each transaction was
+    /// sent to a single node.
+    pub propagation_matrix: Vec<PropagationRow>,
+    pub succeeded_signatures: usize,
+    pub failed_signatures: usize,
+    pub nodes: Vec<NodeName>,
+    pub signatures: Vec<SignatureReport>,
+    /// Ranked nodes first (by score), followed by excluded and send-only nodes.
+    pub summary: Vec<NodeSummary>,
+    /// Δ and send→confirmation statistics for every tracked commitment level.
+    pub commitment_levels: Vec<CommitmentSummary>,
+    /// Count of signatures monitoring gave up on, per node, commitment and reason.
+    pub unconfirmed: Vec<UnconfirmedSummary>,
+    /// Landing latency per compute unit price; empty when no transaction set a priority fee.
+    pub fee_tiers: Vec<FeeTierSummary>,
+    /// Landing latency per payer; empty when a single payer was used.
+    pub payers: Vec<PayerSummary>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnconfirmedSummary {
+    pub node: NodeName,
+    pub commitment: Commitment,
+    pub reason: String,
+    pub count: usize,
+}
+
+/// Latency samples of one node at one commitment level.
+#[derive(Default)]
+struct NodeSamples {
+    deltas: Vec<u64>,
+    /// Δ samples indexed by send position.
+    deltas_by_send_position: Vec<Vec<u64>>,
+    send_to_confirmation: Vec<u64>,
+}
+
+fn samples_at<'a>(
+    samples: &'a BTreeMap<&str, BTreeMap<Commitment, NodeSamples>>,
+    node: &str,
+    commitment: Commitment,
+) -> Option<&'a NodeSamples> {
+    samples
+        .get(node)
+        .and_then(|by_commitment| by_commitment.get(&commitment))
+}
+
+impl Report {
+    /// Build a report comparing confirmation times per signature across nodes.
+    ///
+    /// A node that never confirms one of `run.expected_signatures` at the primary commitment
+    /// (the first of `commitments`) is charged according to `missing_penalty`.
+    /// `run.send_records` provide the send timestamps used for absolute send→ack and
+    /// send→confirmation latencies.
+    pub fn build(
+        run: &RunData,
+        missing_penalty: &MissingPenalty,
+        commitments: &[Commitment],
+    ) -> Self {
+        let primary_commitment = commitments.first().copied().unwrap_or_default();
+        let commitments: Vec<Commitment> = if commitments.is_empty() {
+            vec![primary_commitment]
+        } else {
+            commitments.to_vec()
+        };
+
+        // Step 1: Aggregate data by signature
+        // signature -> (node_name, commitment) -> timestamp_us
+        let mut signature_map: BTreeMap<String, BTreeMap<(String, Commitment), u64>> = run
+            .expected_signatures
+            .iter()
+            .map(|signature| (signature.clone(), BTreeMap::new()))
+            .collect();
+        let mut all_node_names: BTreeSet<String> = BTreeSet::new();
+        // (signature, node_name, commitment) -> reason monitoring gave up
+        let mut unconfirmed_map: BTreeMap<(&str, &str, Commitment), &UnconfirmedReason> =
+            BTreeMap::new();
+        let mut unconfirmed_counts: BTreeMap<(&str, Commitment, String), usize> = BTreeMap::new();
+        // (signature, node_name, commitment) -> notification
+        let mut confirmation_map: BTreeMap<(&str, &str, Commitment), &ConfirmationResult> =
+            BTreeMap::new();
+        // node_name -> (disconnects, gap_us, gave up reconnecting)
+        let mut reconnect_map: BTreeMap<&str, (usize, u64, bool)> = BTreeMap::new();
+
+        let role = |node: &str| run.roles.get(node).copied().unwrap_or_default();
+        // Send-only nodes have no monitoring results, but still send transactions
+        all_node_names.extend(
+            run.roles
+                .iter()
+                .filter(|(_, role)| !role.watches())
+                .map(|(node, _)| node.clone()),
+        );
+        for (node_name, result) in &run.node_results {
+            all_node_names.insert(node_name.clone());
+            for conf in &result.confirmations {
+                signature_map
+                    .entry(conf.signature.clone())
+                    .or_default()
+                    .insert((node_name.clone(), conf.commitment), conf.timestamp_us);
+                confirmation_map.insert(
+                    (conf.signature.as_str(), node_name.as_str(), conf.commitment),
+                    conf,
+                );
+            }
+            if !result.reconnects.is_empty() {
+                let gap_us = result
+                    .reconnects
+                    .iter()
+                    .filter_map(|reconnect| {
+                        reconnect
+                            .reconnected_us
+                            .map(|ts| ts.saturating_sub(reconnect.disconnected_us))
+                    })
+                    .sum();
+                let failed = result
+                    .reconnects
+                    .last()
+                    .is_some_and(|reconnect| reconnect.reconnected_us.is_none());
+                reconnect_map.insert(node_name, (result.reconnects.len(), gap_us, failed));
+            }
+            for entry in &result.unconfirmed {
+                unconfirmed_map.insert(
+                    (
+                        entry.signature.as_str(),
+                        node_name.as_str(),
+                        entry.commitment,
+                    ),
+                    &entry.reason,
+                );
+                *unconfirmed_counts
+                    .entry((
+                        node_name.as_str(),
+                        entry.commitment,
+                        entry.reason.to_string(),
+                    ))
+                    .or_insert(0) += 1;
+            }
+        }
+
+        // (signature, node_name) -> first send record; rebroadcasts only count towards sends
+        let send_map: BTreeMap<(&str, &str), &SendRecord> = run
+            .send_records
+            .iter()
+            .filter(|record| record.attempt == 0)
+            .map(|record| {
+                (
+                    (record.signature.as_str(), record.node_name.as_str()),
+                    record,
+                )
+            })
+            .collect();
+
+        // node_name -> (sends, first send_start_us, last send_start_us)
+        let mut send_spans: BTreeMap<&str, (usize, u64, u64)> = BTreeMap::new();
+        for record in run.send_records.iter().filter(|record| record.attempt == 0) {
+            let span = send_spans.entry(record.node_name.as_str()).or_insert((
+                0,
+                record.send_start_us,
+                record.send_start_us,
+            ));
+            span.0 += 1;
+            span.1 = span.1.min(record.send_start_us);
+            span.2 = span.2.max(record.send_start_us);
+        }
+
+        // (signature, node_name) -> start of every send, rebroadcasts included
+        let mut send_starts: BTreeMap<(&str, &str), Vec<u64>> = BTreeMap::new();
+        for record in &run.send_records {
+            send_starts
+                .entry((record.signature.as_str(), record.node_name.as_str()))
+                .or_default()
+                .push(record.send_start_us);
+        }
+
+        // Step 2: Compute per-signature Δ from fastest at every commitment
+        let mut node_sends_per_landed: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+        let mut samples: BTreeMap<&str, BTreeMap<Commitment, NodeSamples>> = BTreeMap::new();
+        let mut node_send_ack: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+        let mut signatures = Vec::with_capacity(signature_map.len());
+
+        for (signature, node_map) in &signature_map {
+            // Find the fastest timestamp for this signature at each commitment
+            let min_ts: BTreeMap<Commitment, u64> =
+                node_map
+                    .iter()
+                    .fold(BTreeMap::new(), |mut acc, ((_, commitment), &ts)| {
+                        acc.entry(*commitment)
+                            .and_modify(|min: &mut u64| *min = (*min).min(ts))
+                            .or_insert(ts);
+                        acc
+                    });
+            let first_notified_us = min_ts.values().min().copied();
+
+            let mut nodes = Vec::with_capacity(all_node_names.len());
+            for node in &all_node_names {
+                if let (Some(notified_us), Some(starts)) = (
+                    first_notified_us,
+                    send_starts.get(&(signature.as_str(), node.as_str())),
+                ) {
+                    let sends = starts.iter().filter(|&&start| start < notified_us).count();
+                    node_sends_per_landed
+                        .entry(node)
+                        .or_default()
+                        .push(sends.max(1) as u64);
+                }
+                let send = send_map.get(&(signature.as_str(), node.as_str()));
+                if let Some(send) = send {
+                    node_send_ack
+                        .entry(node)
+                        .or_default()
+                        .push(send.send_ack_us.saturating_sub(send.send_start_us));
+                }
+
+                let mut observations = Vec::with_capacity(commitments.len());
+                for &commitment in &commitments {
+                    let confirmed_us = node_map.get(&(node.clone(), commitment)).copied();
+                    let confirmation =
+                        confirmation_map.get(&(signature.as_str(), node.as_str(), commitment));
+                    let delta_us = confirmed_us
+                        .zip(min_ts.get(&commitment))
+                        .map(|(ts, &min)| ts.saturating_sub(min));
+
+                    let node_samples = samples
+                        .entry(node)
+                        .or_default()
+                        .entry(commitment)
+                        .or_default();
+                    if let Some(delta) = delta_us {
+                        node_samples.deltas.push(delta);
+                        if let Some(position) = send.and_then(|send| send.send_position) {
+                            let by_position = &mut node_samples.deltas_by_send_position;
+                            if by_position.len() <= position {
+                                by_position.resize_with(position + 1, Vec::new);
+                            }
+                            by_position[position].push(delta);
+                        }
+                    }
+                    if let (Some(send), Some(ts)) = (send, confirmed_us) {
+                        node_samples
+                            .send_to_confirmation
+                            .push(ts.saturating_sub(send.send_start_us));
+                    }
+
+                    observations.push(CommitmentObservation {
+                        commitment,
+                        confirmed_us,
+                        delta_us,
+                        unconfirmed_reason: unconfirmed_map
+                            .get(&(signature.as_str(), node.as_str(), commitment))
+                            .map(|reason| (*reason).clone()),
+                        resubscribed: confirmation.is_some_and(|conf| conf.resubscribed),
+                        slot: confirmation.map(|conf| conf.slot),
+                        err: confirmation.and_then(|conf| conf.err.clone()),
+                    });
+                }
+
+                nodes.push(NodeObservation {
+                    node: node.clone(),
+                    send_start_us: send.map(|s| s.send_start_us),
+                    send_ack_us: send.map(|s| s.send_ack_us),
+                    send_position: send.and_then(|s| s.send_position),
+                    send_error: send.and_then(|s| s.error),
+                    commitments: observations,
+                });
+            }
+
+            let primary_observations = || nodes.iter().map(|node| node.primary());
+            let landing_slot = primary_observations().filter_map(|obs| obs.slot).min();
+            let err = primary_observations().find_map(|obs| obs.err.clone());
+            let status = match (landing_slot, &err) {
+                (None, _) => TransactionStatus::NotLanded,
+                (Some(_), Some(_)) => TransactionStatus::Failed,
+                (Some(_), None) => TransactionStatus::Succeeded,
+            };
+            let first_sent_us = nodes.iter().filter_map(|node| node.send_start_us).min();
+            let first_confirmed_us = primary_observations()
+                .filter_map(|obs| obs.confirmed_us)
+                .min();
+            signatures.push(SignatureReport {
+                signature: signature.clone(),
+                status,
+                landing_slot,
+                err,
+                compute_unit_price: run.compute_unit_prices.get(signature).copied(),
+                payer: run.payers.get(signature).cloned(),
+                landing_latency_us: first_sent_us
+                    .zip(first_confirmed_us)
+                    .map(|(sent, confirmed)| confirmed.saturating_sub(sent)),
+                nodes,
+            });
+        }
+
+        // Step 3: Score nodes at the primary commitment, charging missed signatures per the
+        // penalty model
+        let total_signatures = signature_map.len();
+        let empty_samples = NodeSamples::default();
+        let primary_samples =
+            |node: &str| samples_at(&samples, node, primary_commitment).unwrap_or(&empty_samples);
+        let max_observed_delta = all_node_names
+            .iter()
+            .flat_map(|node| primary_samples(node).deltas.iter().copied())
+            .max()
+            .unwrap_or(0);
+        let per_miss_penalty = match missing_penalty {
+            MissingPenalty::Fixed { penalty_us } => *penalty_us,
+            MissingPenalty::MaxObserved => max_observed_delta,
+            MissingPenalty::Exclude => 0,
+        };
+
+        let (mut ranked, excluded): (Vec<NodeSummary>, Vec<NodeSummary>) = all_node_names
+            .iter()
+            .map(|node| {
+                let node = node.as_str();
+                let node_samples = primary_samples(node);
+                let role = role(node);
+                let confirmed = node_samples.deltas.len();
+                let missed = if role.watches() {
+                    total_signatures - confirmed
+                } else {
+                    0
+                };
+                let sum_delta_us = node_samples.deltas.iter().sum();
+                let timed_out = unconfirmed_map
+                    .iter()
+                    .filter(|((_, unconfirmed_node, commitment), reason)| {
+                        *unconfirmed_node == node
+                            && *commitment == primary_commitment
+                            && !matches!(
+                                reason,
+                                UnconfirmedReason::ConnectionClosed
+                                    | UnconfirmedReason::MonitorFailed
+                            )
+                    })
+                    .count();
+                let penalty_us = per_miss_penalty.saturating_mul(missed as u64);
+                let mut delta_histogram = vec![0; HISTOGRAM_BUCKETS_US.len() + 1];
+                for &delta in &node_samples.deltas {
+                    delta_histogram[histogram_bucket_index(delta)] += 1;
+                }
+                let (disconnects, reconnect_gap_us, reconnect_failed) =
+                    reconnect_map.get(node).copied().unwrap_or_default();
+                let primary_confirmations = || {
+                    confirmation_map
+                        .iter()
+                        .filter(|((_, confirmed_node, commitment), _)| {
+                            *confirmed_node == node && *commitment == primary_commitment
+                        })
+                        .map(|(_, conf)| conf)
+                };
+                let failed = primary_confirmations()
+                    .filter(|conf| conf.err.is_some())
+                    .count();
+                let resubscribed = primary_confirmations()
+                    .filter(|conf| conf.resubscribed)
+                    .count();
+                NodeSummary {
+                    rank: None,
+                    node: node.to_string(),
+                    role,
+                    confirmed,
+                    failed,
+                    missed,
+                    timed_out,
+                    success_rate: if total_signatures == 0 {
+                        0.0
+                    } else {
+                        confirmed as f64 / total_signatures as f64
+                    },
+                    sum_delta_us,
+                    penalty_us,
+                    score_us: Some(sum_delta_us.saturating_add(penalty_us)),
+                    delta: LatencyStats::from_samples(&node_samples.deltas),
+                    delta_histogram,
+                    send_to_ack: node_send_ack
+                        .get(node)
+                        .and_then(|samples| LatencyStats::from_samples(samples)),
+                    send_to_confirmation: LatencyStats::from_samples(
+                        &node_samples.send_to_confirmation,
+                    ),
+                    disconnects,
+                    reconnect_gap_us,
+                    reconnect_failed,
+                    resubscribed,
+                    send_rate_tps: send_spans.get(node).and_then(|&(sends, first, last)| {
+                        // n sends span n - 1 gaps
+                        (sends > 1 && last > first)
+                            .then(|| (sends - 1) as f64 * 1_000_000.0 / (last - first) as f64)
+                    }),
+                    sent_first: run
+                        .send_records
+                        .iter()
+                        .filter(|record| {
+                            record.node_name == node && record.send_position == Some(0)
+                        })
+                        .count(),
+                    delta_by_send_position: node_samples
+                        .deltas_by_send_position
+                        .iter()
+                        .map(|samples| LatencyStats::from_samples(samples))
+                        .collect(),
+                    send_errors: run
+                        .send_records
+                        .iter()
+                        .filter(|record| record.node_name == node)
+                        .filter_map(|record| record.error)
+                        .fold(BTreeMap::new(), |mut counts, kind| {
+                            *counts.entry(kind).or_insert(0) += 1;
+                            counts
+                        }),
+                    send_options: run.send_options.get(node).copied(),
+                    sends: run
+                        .send_records
+                        .iter()
+                        .filter(|record| record.node_name == node)
+                        .count(),
+                    sends_per_landed: node_sends_per_landed
+                        .get(node)
+                        .and_then(|counts| SendCountStats::from_counts(counts)),
+                }
+            })
+            .partition(|summary| {
+                summary.role.watches()
+                    && (!matches!(missing_penalty, MissingPenalty::Exclude) || summary.missed == 0)
+            });
+        ranked.sort_by_key(|summary| (summary.score_us, summary.missed));
+        for (i, summary) in ranked.iter_mut().enumerate() {
+            summary.rank = Some(i + 1);
+        }
+
+        let mut summary = ranked;
+        summary.extend(excluded.into_iter().map(|summary| NodeSummary {
+            score_us: None,
+            ..summary
+        }));
+
+        // Step 4: Per-commitment statistics of the watched nodes
+        let commitment_levels = commitments
+            .iter()
+            .map(|&commitment| CommitmentSummary {
+                commitment,
+                nodes: all_node_names
+                    .iter()
+                    .filter(|node| role(node).watches())
+                    .map(|node| {
+                        let node_samples =
+                            samples_at(&samples, node, commitment).unwrap_or(&empty_samples);
+                        CommitmentNodeSummary {
+                            node: node.clone(),
+                            confirmed: node_samples.deltas.len(),
+                            delta: LatencyStats::from_samples(&node_samples.deltas),
+                            send_to_confirmation: LatencyStats::from_samples(
+                                &node_samples.send_to_confirmation,
+                            ),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        // Step 5: Landing latency per fee tier
+        let mut tiers: BTreeMap<Option<u64>, Vec<&SignatureReport>> = BTreeMap::new();
+        if !run.compute_unit_prices.is_empty() {
+            for signature in &signatures {
+                tiers
+                    .entry(signature.compute_unit_price)
+                    .or_default()
+                    .push(signature);
+            }
+        }
+        let fee_tiers = tiers
+            .into_iter()
+            .map(|(compute_unit_price, tier)| FeeTierSummary {
+                compute_unit_price,
+                stats: LandingStats::from_signatures(&tier),
+            })
+            .collect();
+
+        // Step 6: Landing latency per payer
+        let mut by_payer: BTreeMap<&str, Vec<&SignatureReport>> = BTreeMap::new();
+        for signature in &signatures {
+            if let Some(payer) = &signature.payer {
+                by_payer.entry(payer).or_default().push(signature);
+            }
+        }
+        let payers = if by_payer.len() > 1 {
+            by_payer
+                .into_iter()
+                .map(|(payer, group)| PayerSummary {
+                    payer: payer.to_string(),
+                    stats: LandingStats::from_signatures(&group),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        // Step 7: Sender × observer latency when each transaction went through a single node
+        let mut propagation_matrix = Vec::new();
+        if run.propagation.is_some() {
+            // sender -> (transactions, observer -> send→notification samples)
+            let mut by_sender: BTreeMap<&str, (usize, BTreeMap<&str, Vec<u64>>)> = BTreeMap::new();
+            for signature in &signatures {
+                let mut senders = signature
+                    .nodes
+                    .iter()
+                    .filter_map(|node| node.send_start_us.map(|sent| (node, sent)));
+                let (Some((sender, sent_us)), None) = (senders.next(), senders.next()) else {
+                    continue;
+                };
+                let (transactions, observers) = by_sender.entry(&sender.node).or_default();
+                *transactions += 1;
+                for observer in &signature.nodes {
+                    if let Some(confirmed_us) = observer.primary().confirmed_us {
+                        observers
+                            .entry(&observer.node)
+                            .or_default()
+                            .push(confirmed_us.saturating_sub(sent_us));
+                    }
+                }
+            }
+            propagation_matrix = by_sender
+                .into_iter()
+                .map(|(sender, (transactions, observers))| PropagationRow {
+                    sender: sender.to_string(),
+                    transactions,
+                    observers: all_node_names
+                        .iter()
+                        .filter(|observer| role(observer).watches())
+                        .map(|observer| PropagationCell {
+                            observer: observer.clone(),
+                            latency: observers
+                                .get(observer.as_str())
+                                .and_then(|samples| LatencyStats::from_samples(samples)),
+                        })
+                        .collect(),
+                })
+                .collect();
+        }
+
+        let count_status = |status| {
+            signatures
+                .iter()
+                .filter(|signature| signature.status == status)
+                .count()
+        };
+
+        Self {
+            missing_penalty: missing_penalty.clone(),
+            commitments,
+            total_signatures,
+            build: run.build.map(|stats| BuildSummary {
+                transactions: stats.transactions,
+                elapsed_us: stats.elapsed.as_micros() as u64,
+                transactions_per_second: stats.transactions_per_second(),
+                blockhash_fetches: stats.blockhash_fetches,
+                nonce_fetches: stats.nonce_fetches,
+            }),
+            pacing: run.pacing,
+            fairness: run.fairness,
+            rebroadcast: run.rebroadcast,
+            propagation: run.propagation.clone(),
+            index_memo: run.index_memo,
+            propagation_matrix,
+            succeeded_signatures: count_status(TransactionStatus::Succeeded),
+            failed_signatures: count_status(TransactionStatus::Failed),
+            nodes: all_node_names.into_iter().collect(),
+            signatures,
+            summary,
+            commitment_levels,
+            unconfirmed: unconfirmed_counts
+                .into_iter()
+                .map(|((node, commitment, reason), count)| UnconfirmedSummary {
+                    node: node.to_string(),
+                    commitment,
+                    reason,
+                    count,
+                })
+                .collect(),
+            fee_tiers,
+            payers,
+        }
+    }
+
+    /// Whether `node` was watched, i.e. has confirmations to report.
+    pub fn watches(&self, node: &str) -> bool {
+        self.summary
+            .iter()
+            .find(|summary| summary.node == node)
+            .is_none_or(|summary| summary.role.watches())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        markdown::render(self)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Render as CSV: the per-(signature, node) raw data and the per-node summary table.
+    pub fn to_csv(&self) -> (String, String) {
+        (csv::render_observations(self), csv::render_summary(self))
+    }
+}
+
+/// Render `report` in `format` and write it to `output`, or to the log (markdown) / stdout
+/// (json) when no output path is given. CSV needs an output path.
+pub fn write_report(report: &Report, format: OutputFormat, output: Option<&Path>) -> Result<()> {
+    match format {
+        OutputFormat::Markdown => {
+            let markdown = report.to_markdown();
+            match output {
+                Some(path) => std::fs::write(path, markdown)?,
+                None => tracing::info!("{}", markdown),
+            }
+        }
+        OutputFormat::Json => {
+            let json = report.to_json()?;
+            match output {
+                Some(path) => std::fs::write(path, json)?,
+                None => println!("{}", json),
+            }
+        }
+        OutputFormat::Csv => {
+            // Two tables with different columns cannot share one CSV stream
+            let path = output.ok_or_else(|| anyhow::anyhow!("CSV reports require --output"))?;
+            let (observations, summary) = report.to_csv();
+            std::fs::write(path, observations)?;
+            std::fs::write(path.with_extension("summary.csv"), summary)?;
+        }
+    }
+
+    if let Some(path) = output {
+        tracing::info!("Report written to {:?}", path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn confirmation(signature: &str, timestamp_us: u64) -> ConfirmationResult {
+        ConfirmationResult {
+            signature: signature.to_string(),
+            commitment: Commitment::Processed,
+            timestamp_us,
+            resubscribed: false,
+            slot: 100,
+            err: None,
+        }
+    }
+
+    fn send(signature: &str, node: &str, send_start_us: u64) -> SendRecord {
+        SendRecord {
+            signature: signature.to_string(),
+            node_name: node.to_string(),
+            send_start_us,
+            send_ack_us: send_start_us + 50,
+            send_position: None,
+            attempt: 0,
+            error: None,
+        }
+    }
+
+    /// `node`'s monitor result with `confirmations`.
+    fn watched(node: &str, confirmations: Vec<ConfirmationResult>) -> (NodeName, MonitorResult) {
+        (
+            node.to_string(),
+            MonitorResult {
+                confirmations,
+                ..MonitorResult::default()
+            },
+        )
+    }
+
+    fn signatures(signatures: &[&str]) -> Vec<String> {
+        signatures
+            .iter()
+            .map(|signature| signature.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn delta_is_measured_from_the_fastest_node() {
+        let run = RunData {
+            expected_signatures: signatures(&["s1", "s2"]),
+            node_results: vec![
+                watched("a", vec![confirmation("s1", 100), confirmation("s2", 700)]),
+                watched("b", vec![confirmation("s1", 600), confirmation("s2", 200)]),
+                watched("c", vec![confirmation("s1", 4_100)]),
+            ],
+            ..RunData::default()
+        };
+        let report = Report::build(&run, &MissingPenalty::MaxObserved, &[]);
+        let deltas = |signature: &str| -> Vec<Option<u64>> {
+            report
+                .signatures
+                .iter()
+                .find(|report| report.signature == signature)
+                .unwrap()
+                .nodes
+                .iter()
+                .map(|node| node.primary().delta_us)
+                .collect()
+        };
+        assert_eq!(deltas("s1"), [Some(0), Some(500), Some(4_000)]);
+        assert_eq!(deltas("s2"), [Some(500), Some(0), None]);
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let samples: Vec<u64> = (1..=100).rev().collect();
+        let stats = LatencyStats::from_samples(&samples).unwrap();
+        assert_eq!(
+            (stats.p50, stats.p90, stats.p95, stats.p99, stats.max),
+            (50, 90, 95, 99, 100)
+        );
+        assert_eq!(stats.mean, 50.5);
+
+        let stats = LatencyStats::from_samples(&[7]).unwrap();
+        assert_eq!((stats.p50, stats.p99, stats.std_dev), (7, 7, 0.0));
+        assert!(LatencyStats::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn histogram_buckets_are_exclusive_upper_bounds() {
+        assert_eq!(histogram_bucket_index(0), 0);
+        assert_eq!(histogram_bucket_index(1), 1);
+        assert_eq!(histogram_bucket_index(999), 1);
+        assert_eq!(histogram_bucket_index(1_000), 2);
+        assert_eq!(histogram_bucket_index(u64::MAX), HISTOGRAM_BUCKETS_US.len());
+    }
+
+    fn summary<'a>(report: &'a Report, node: &str) -> &'a NodeSummary {
+        report
+            .summary
+            .iter()
+            .find(|summary| summary.node == node)
+            .unwrap()
+    }
+
+    /// `a` is first on `s1` and 50 μs behind on `s2`, `b` 50 μs behind on `s1` and misses `s2`,
+    /// and `c` 1 ms behind on `s1` but first on `s2`.
+    fn run_with_a_miss() -> RunData {
+        RunData {
+            expected_signatures: signatures(&["s1", "s2"]),
+            node_results: vec![
+                watched("a", vec![confirmation("s1", 100), confirmation("s2", 250)]),
+                watched("b", vec![confirmation("s1", 150)]),
+                watched(
+                    "c",
+                    vec![confirmation("s1", 1_100), confirmation("s2", 200)],
+                ),
+            ],
+            ..RunData::default()
+        }
+    }
+
+    fn ranking(report: &Report) -> Vec<(Option<usize>, &str, Option<u64>)> {
+        report
+            .summary
+            .iter()
+            .map(|summary| (summary.rank, summary.node.as_str(), summary.score_us))
+            .collect()
+    }
+
+    #[test]
+    fn missed_signatures_are_charged_the_largest_observed_delta() {
+        let report = Report::build(&run_with_a_miss(), &MissingPenalty::MaxObserved, &[]);
+        assert_eq!(
+            ranking(&report),
+            [
+                (Some(1), "a", Some(50)),
+                (Some(2), "c", Some(1_000)),
+                (Some(3), "b", Some(1_050)),
+            ]
+        );
+        let b = summary(&report, "b");
+        assert_eq!((b.confirmed, b.missed), (1, 1));
+        assert_eq!((b.sum_delta_us, b.penalty_us), (50, 1_000));
+        assert_eq!(b.success_rate, 0.5);
+    }
+
+    #[test]
+    fn fixed_penalty_can_change_the_ranking() {
+        let report = Report::build(
+            &run_with_a_miss(),
+            &MissingPenalty::Fixed { penalty_us: 100 },
+            &[],
+        );
+        assert_eq!(
+            ranking(&report),
+            [
+                (Some(1), "a", Some(50)),
+                (Some(2), "b", Some(150)),
+                (Some(3), "c", Some(1_000)),
+            ]
+        );
+        assert_eq!(summary(&report, "b").penalty_us, 100);
+    }
+
+    #[test]
+    fn excluded_nodes_are_listed_after_the_ranking() {
+        let report = Report::build(&run_with_a_miss(), &MissingPenalty::Exclude, &[]);
+        assert_eq!(
+            ranking(&report),
+            [
+                (Some(1), "a", Some(50)),
+                (Some(2), "c", Some(1_000)),
+                (None, "b", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_node_whose_monitor_failed_is_charged_for_every_signature() {
+        let expected_signatures = signatures(&["s1", "s2"]);
+        let run = RunData {
+            node_results: vec![
+                watched("a", vec![confirmation("s1", 100), confirmation("s2", 200)]),
+                watched("b", vec![confirmation("s1", 400), confirmation("s2", 200)]),
+                (
+                    "broken".to_string(),
+                    MonitorResult::failed(&expected_signatures, &[Commitment::Processed]),
+                ),
+            ],
+            expected_signatures,
+            ..RunData::default()
+        };
+        let report = Report::build(&run, &MissingPenalty::MaxObserved, &[]);
+        assert_eq!(ranking(&report)[2], (Some(3), "broken", Some(600)));
+        let broken = summary(&report, "broken");
+        assert_eq!((broken.missed, broken.timed_out), (2, 0));
+    }
+
+    #[test]
+    fn absolute_latencies_are_measured_from_the_send() {
+        // `b` is sent `s1` later but notified first, so `s1` lands 9 ms after its first send
+        let run = RunData {
+            expected_signatures: signatures(&["s1", "s2"]),
+            send_records: vec![
+                send("s1", "a", 0),
+                send("s2", "a", 1_000),
+                send("s1", "b", 500),
+            ],
+            node_results: vec![
+                watched(
+                    "a",
+                    vec![confirmation("s1", 10_000), confirmation("s2", 12_000)],
+                ),
+                watched("b", vec![confirmation("s1", 9_000)]),
+            ],
+            ..RunData::default()
+        };
+        let report = Report::build(&run, &MissingPenalty::MaxObserved, &[]);
+        let a = summary(&report, "a");
+        assert_eq!(a.send_to_ack.as_ref().unwrap().max, 50);
+        let send_to_confirmation = a.send_to_confirmation.as_ref().unwrap();
+        assert_eq!(send_to_confirmation.count, 2);
+        assert_eq!(send_to_confirmation.max, 11_000);
+        assert_eq!(
+            summary(&report, "b")
+                .send_to_confirmation
+                .as_ref()
+                .unwrap()
+                .max,
+            8_500
+        );
+        assert_eq!(report.signatures[0].landing_latency_us, Some(9_000));
+    }
+
+    #[test]
+    fn fee_tiers_group_signatures_by_compute_unit_price() {
+        // `s1` pays a priority fee and lands after 5 ms, `s2` does not and lands after 10 ms
+        let run = RunData {
+            expected_signatures: signatures(&["s1", "s2"]),
+            compute_unit_prices: BTreeMap::from([("s1".to_string(), 1_000)]),
+            send_records: vec![send("s1", "a", 0), send("s2", "a", 1_000)],
+            node_results: vec![watched(
+                "a",
+                vec![confirmation("s1", 5_000), confirmation("s2", 11_000)],
+            )],
+            ..RunData::default()
+        };
+        let report = Report::build(&run, &MissingPenalty::MaxObserved, &[]);
+        let tiers: Vec<_> = report
+            .fee_tiers
+            .iter()
+            .map(|tier| {
+                (
+                    tier.compute_unit_price,
+                    tier.stats.transactions,
+                    tier.stats.landed,
+                    tier.stats
+                        .landing_latency
+                        .as_ref()
+                        .map(|latency| latency.p50),
+                )
+            })
+            .collect();
+        assert_eq!(
+            tiers,
+            [(None, 1, 1, Some(10_000)), (Some(1_000), 1, 1, Some(5_000))]
+        );
+
+        let run = RunData {
+            compute_unit_prices: BTreeMap::new(),
+            ..run
+        };
+        let report = Report::build(&run, &MissingPenalty::MaxObserved, &[]);
+        assert!(report.fee_tiers.is_empty());
+    }
+
+    #[test]
+    fn payers_are_summarized_only_with_several_payers() {
+        // `p1` pays for `s1` and `s3`, which does not land, and `p2` for `s2`
+        let mut run = RunData {
+            expected_signatures: signatures(&["s1", "s2", "s3"]),
+            payers: BTreeMap::from([
+                ("s1".to_string(), "p1".to_string()),
+                ("s2".to_string(), "p2".to_string()),
+                ("s3".to_string(), "p1".to_string()),
+            ]),
+            send_records: vec![send("s1", "a", 0), send("s2", "a", 0), send("s3", "a", 0)],
+            node_results: vec![watched(
+                "a",
+                vec![confirmation("s1", 1_000), confirmation("s2", 2_000)],
+            )],
+            ..RunData::default()
+        };
+        let report = Report::build(&run, &MissingPenalty::MaxObserved, &[]);
+        let payers: Vec<_> = report
+            .payers
+            .iter()
+            .map(|payer| {
+                (
+                    payer.payer.as_str(),
+                    payer.stats.transactions,
+                    payer.stats.landed,
+                )
+            })
+            .collect();
+        assert_eq!(payers, [("p1", 2, 1), ("p2", 1, 1)]);
+
+        run.payers.insert("s2".to_string(), "p1".to_string());
+        let report = Report::build(&run, &MissingPenalty::MaxObserved, &[]);
+        assert!(report.payers.is_empty());
+    }
+
+    #[test]
+    fn propagation_matrix_splits_latency_by_sender_and_observer() {
+        // `s1` goes through `a` only and `s2` through `b` only; `c` never sees `s2`
+        let observed = || {
+            vec![
+                watched(
+                    "a",
+                    vec![confirmation("s1", 10_000), confirmation("s2", 12_000)],
+                ),
+                watched(
+                    "b",
+                    vec![confirmation("s1", 10_500), confirmation("s2", 11_000)],
+                ),
+                watched("c", vec![confirmation("s1", 14_000)]),
+            ]
+        };
+        let run = RunData {
+            expected_signatures: signatures(&["s1", "s2"]),
+            propagation: Some(PropagationConfig::RoundRobin),
+            send_records: vec![send("s1", "a", 0), send("s2", "b", 1_000)],
+            node_results: observed(),
+            ..RunData::default()
+        };
+        let report = Report::build(&run, &MissingPenalty::MaxObserved, &[]);
+        let matrix: Vec<_> = report
+            .propagation_matrix
+            .iter()
+            .map(|row| {
+                let cells: Vec<_> = row
+                    .observers
+                    .iter()
+                    .map(|cell| {
+                        let p50 = cell.latency.as_ref().map(|latency| latency.p50);
+                        (cell.observer.as_str(), p50)
+                    })
+                    .collect();
+                (row.sender.as_str(), row.transactions, cells)
+            })
+            .collect();
+        assert_eq!(
+            matrix,
+            [
+                (
+                    "a",
+                    1,
+                    vec![
+                        ("a", Some(10_000)),
+                        ("b", Some(10_500)),
+                        ("c", Some(14_000))
+                    ]
+                ),
+                (
+                    "b",
+                    1,
+                    vec![("a", Some(11_000)), ("b", Some(10_000)), ("c", None)]
+                ),
+            ]
+        );
+
+        // Transactions sent to several nodes have no single sender
+        let run = RunData {
+            expected_signatures: signatures(&["s1", "s2"]),
+            propagation: Some(PropagationConfig::RoundRobin),
+            send_records: vec![
+                send("s1", "a", 0),
+                send("s1", "b", 0),
+                send("s2", "a", 1_000),
+                send("s2", "b", 1_000),
+            ],
+            node_results: observed(),
+            ..RunData::default()
+        };
+        let report = Report::build(&run, &MissingPenalty::MaxObserved, &[]);
+        assert!(report.propagation_matrix.is_empty());
+    }
+}
@@ -1,81 +1,730 @@
+use crate::clock;
+use crate::config::{
+    BenchmarkConfig, Commitment, FairnessConfig, PropagationConfig, RebroadcastConfig,
+    SendEncoding, SendOptions,
+};
+use crate::events::{Event, EventLog};
+use crate::transaction::BlockhashExpiry;
+use anyhow::{Context, Result};
+use futures::stream::{self, StreamExt};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use solana_client::client_error::reqwest::{self, header::HeaderMap, StatusCode};
+use solana_client::client_error::{ClientError, ClientErrorKind};
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::{commitment_config::CommitmentConfig, transaction::Transaction};
-use std::sync::{mpsc, Arc};
-use std::thread;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_rpc_client::http_sender::HttpSender;
+use solana_rpc_client::rpc_client::RpcClientConfig;
+use solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    signature::Signature,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use solana_transaction_status::UiTransactionEncoding;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{watch, Barrier, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+
+/// Why a `sendTransaction` call failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SendErrorKind {
+    /// HTTP 429, after the client's own retries.
+    RateLimited,
+    Timeout,
+    BlockhashNotFound,
+    /// Preflight simulation rejected the transaction.
+    PreflightFailure,
+    AlreadyProcessed,
+    /// The node could not be reached.
+    Connection,
+    Other,
+}
+
+impl SendErrorKind {
+    pub const ALL: [SendErrorKind; 7] = [
+        SendErrorKind::RateLimited,
+        SendErrorKind::Timeout,
+        SendErrorKind::BlockhashNotFound,
+        SendErrorKind::PreflightFailure,
+        SendErrorKind::AlreadyProcessed,
+        SendErrorKind::Connection,
+        SendErrorKind::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SendErrorKind::RateLimited => "rate_limited",
+            SendErrorKind::Timeout => "timeout",
+            SendErrorKind::BlockhashNotFound => "blockhash_not_found",
+            SendErrorKind::PreflightFailure => "preflight_failure",
+            SendErrorKind::AlreadyProcessed => "already_processed",
+            SendErrorKind::Connection => "connection",
+            SendErrorKind::Other => "other",
+        }
+    }
+
+    fn classify(error: &ClientError) -> Self {
+        match error.kind() {
+            ClientErrorKind::Reqwest(e) if e.status() == Some(StatusCode::TOO_MANY_REQUESTS) => {
+                SendErrorKind::RateLimited
+            }
+            ClientErrorKind::Reqwest(e) if e.is_timeout() => SendErrorKind::Timeout,
+            ClientErrorKind::Reqwest(e) if e.is_connect() || e.is_request() => {
+                SendErrorKind::Connection
+            }
+            ClientErrorKind::Io(_) => SendErrorKind::Connection,
+            _ => match error.get_transaction_error() {
+                Some(TransactionError::BlockhashNotFound) => SendErrorKind::BlockhashNotFound,
+                Some(TransactionError::AlreadyProcessed) => SendErrorKind::AlreadyProcessed,
+                Some(_) => SendErrorKind::PreflightFailure,
+                None => SendErrorKind::from_message(&error.to_string()),
+            },
+        }
+    }
+
+    /// Best-effort classification of an error message, for errors without structured details
+    /// and event logs recorded before send errors were classified.
+    pub fn from_message(message: &str) -> Self {
+        let message = message.to_lowercase();
+        let mentions = |needles: &[&str]| needles.iter().any(|needle| message.contains(needle));
+        if mentions(&["429", "too many requests"]) {
+            SendErrorKind::RateLimited
+        } else if mentions(&["timed out", "timeout"]) {
+            SendErrorKind::Timeout
+        } else if mentions(&["blockhash not found"]) {
+            SendErrorKind::BlockhashNotFound
+        } else if mentions(&["already been processed", "already processed"]) {
+            SendErrorKind::AlreadyProcessed
+        } else if mentions(&["simulation failed", "preflight"]) {
+            SendErrorKind::PreflightFailure
+        } else if mentions(&["error sending request", "connection"]) {
+            SendErrorKind::Connection
+        } else {
+            SendErrorKind::Other
+        }
+    }
+}
+
+impl std::fmt::Display for SendErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Timing of a single `sendTransaction` call to a single node, on the `clock` timeline.
+#[derive(Debug, Clone)]
+pub struct SendRecord {
+    pub signature: String,
+    pub node_name: String,
+    pub send_start_us: u64,
+    pub send_ack_us: u64,
+    /// How many nodes started sending this transaction before this one; `None` in event logs
+    /// recorded before send order was tracked.
+    pub send_position: Option<usize>,
+    /// 0 for the first send to the node, then 1, 2, ... for rebroadcasts.
+    pub attempt: usize,
+    pub error: Option<SendErrorKind>,
+}
+
+/// First send time of each signature across all nodes, shared with the WebSocket monitors so
+/// per-signature timeouts start when a transaction actually leaves the client. In turn, the
+/// monitors mark signatures they were notified of, which stops their rebroadcasts.
+#[derive(Clone, Default)]
+pub struct SendTracker {
+    first_sent_us: Arc<Mutex<HashMap<Signature, u64>>>,
+    notified: Arc<Mutex<HashSet<Signature>>>,
+}
+
+impl SendTracker {
+    pub fn record(&self, signature: Signature, timestamp_us: u64) {
+        self.first_sent_us
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(signature)
+            .or_insert(timestamp_us);
+    }
+
+    pub fn first_sent_us(&self, signature: &Signature) -> Option<u64> {
+        self.first_sent_us
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(signature)
+            .copied()
+    }
+
+    pub fn record_notified(&self, signature: Signature) {
+        self.notified
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(signature);
+    }
+
+    pub fn notified(&self, signature: &Signature) -> bool {
+        self.notified
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(signature)
+    }
+}
+
+/// HTTP client of `url` at `commitment`, sending `headers` with every request on top of the
+/// Solana client's own.
+pub fn http_client(
+    url: &str,
+    headers: &HeaderMap,
+    commitment: CommitmentConfig,
+) -> Result<RpcClient> {
+    let mut header_map = HttpSender::default_headers();
+    header_map.extend(headers.clone());
+    // Same timeouts as `RpcClient::new_with_commitment`
+    let timeout = Duration::from_secs(30);
+    let client = reqwest::Client::builder()
+        .default_headers(header_map)
+        .timeout(timeout)
+        .pool_idle_timeout(timeout)
+        .build()?;
+    Ok(RpcClient::new_sender(
+        HttpSender::new_with_client(url, client),
+        RpcClientConfig::with_commitment(commitment),
+    ))
+}
+
+/// Coordinates the sends of one transaction across nodes.
+struct SendGate {
+    barrier: Option<Barrier>,
+    /// Node indices in the order they start sending, if randomized.
+    order: Option<Vec<usize>>,
+    /// Nodes that have started sending so far.
+    started: watch::Sender<usize>,
+}
+
+impl SendGate {
+    /// Gate for sending one transaction to the nodes with the indices `senders`.
+    fn new(mut senders: Vec<usize>, fairness: FairnessConfig, rng: &mut impl rand::Rng) -> Self {
+        Self {
+            barrier: fairness.barrier.then(|| Barrier::new(senders.len())),
+            order: fairness.randomize_order.then(|| {
+                senders.shuffle(rng);
+                senders
+            }),
+            started: watch::Sender::new(0),
+        }
+    }
+
+    /// Wait until `node` may start sending, and return its position among the nodes.
+    async fn enter(&self, node: usize) -> usize {
+        if let Some(barrier) = &self.barrier {
+            barrier.wait().await;
+        }
+        if let Some(order) = &self.order {
+            let turn = order.iter().position(|&n| n == node).unwrap_or_default();
+            // Cannot fail: the sender lives in `self`
+            let _ = self
+                .started
+                .subscribe()
+                .wait_for(|&started| started >= turn)
+                .await;
+        }
+        let mut position = 0;
+        self.started.send_modify(|started| {
+            position = *started;
+            *started += 1;
+        });
+        position
+    }
+}
+
+/// HTTP client of one node, how many `sendTransaction` calls may be in flight to it and the
+/// options they use.
+struct NodeClient {
+    name: String,
+    client: RpcClient,
+    concurrency: usize,
+    /// `concurrency` permits, held by every call in flight, first sends and rebroadcasts alike.
+    permits: Semaphore,
+    /// Permits rebroadcasts take before one of `permits`, fewer than `concurrency` by the share
+    /// of a burst and at least one, so rebroadcasts never hold up a burst or a first send.
+    rebroadcast_permits: Semaphore,
+    send_config: RpcSendTransactionConfig,
+}
+
+/// The `sendTransaction` config `options` translate to, for a client at `commitment`.
+fn send_config(options: &SendOptions, commitment: CommitmentConfig) -> RpcSendTransactionConfig {
+    RpcSendTransactionConfig {
+        skip_preflight: options.skip_preflight.unwrap_or_default(),
+        // Like `RpcClient::send_transaction`, simulate at the client's commitment by default
+        preflight_commitment: Some(options.preflight_commitment.map_or(
+            commitment.commitment,
+            |commitment| match commitment {
+                Commitment::Processed => CommitmentLevel::Processed,
+                Commitment::Confirmed => CommitmentLevel::Confirmed,
+                Commitment::Finalized => CommitmentLevel::Finalized,
+            },
+        )),
+        encoding: options.encoding.map(|encoding| match encoding {
+            SendEncoding::Base58 => UiTransactionEncoding::Base58,
+            SendEncoding::Base64 => UiTransactionEncoding::Base64,
+        }),
+        max_retries: options.max_retries,
+        min_context_slot: options.min_context_slot,
+    }
+}
+
+/// How often the block height is polled while rebroadcasting, about once per slot.
+const BLOCK_HEIGHT_POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Settings and state every rebroadcast of a run shares.
+#[derive(Clone)]
+struct Rebroadcaster {
+    settings: RebroadcastConfig,
+    expiry: Arc<BlockhashExpiry>,
+    /// Latest polled block height, 0 until the first poll.
+    block_height: watch::Receiver<u64>,
+}
+
+impl Rebroadcaster {
+    /// Whether `transaction` can no longer land, because a node did not find its recent
+    /// blockhash after a send failed with `error`, or the blockhash is past its last valid block
+    /// height. A durable nonce transaction only fails that way once its nonce has been consumed
+    /// or advanced, and never expires otherwise.
+    fn expired(&self, transaction: &VersionedTransaction, error: Option<SendErrorKind>) -> bool {
+        error == Some(SendErrorKind::BlockhashNotFound)
+            || self
+                .expiry
+                .get(transaction.message.recent_blockhash())
+                .is_some_and(|&last_valid| *self.block_height.borrow() > last_valid)
+    }
+}
+
+/// Which nodes each transaction is sent to.
+enum Senders {
+    All,
+    RoundRobin,
+    /// Index of the only sender.
+    Fixed(usize),
+}
 
 pub struct RpcClientManager {
-    clients: Vec<Arc<RpcClient>>,
+    clients: Vec<Arc<NodeClient>>,
+    senders: Senders,
+    fairness: FairnessConfig,
+    rebroadcast: Option<RebroadcastConfig>,
+    /// Client polling the block height while rebroadcasting.
+    height_client: Option<Arc<RpcClient>>,
+    event_log: Arc<EventLog>,
+    send_tracker: SendTracker,
 }
 
 impl RpcClientManager {
-    pub fn new(rpc_urls: Vec<String>) -> Self {
-        let clients = rpc_urls
-            .into_iter()
+    /// Clients of the sending `config.rpc_nodes`, with the send settings of `config`.
+    pub fn new(
+        config: &BenchmarkConfig,
+        event_log: Arc<EventLog>,
+        send_tracker: SendTracker,
+    ) -> Result<Self> {
+        let clients: Vec<Arc<NodeClient>> = config
+            .send_nodes()
             // TODO: @kero what use of commitment config?
-            .map(|url| {
-                Arc::new(RpcClient::new_with_commitment(
-                    url,
+            .map(|(node, http_url)| {
+                let client = http_client(
+                    http_url,
+                    &node.resolved_headers,
                     CommitmentConfig::confirmed(),
-                ))
+                )
+                .with_context(|| format!("Failed to create HTTP client of {}", node.name))?;
+                let concurrency = config.send_concurrency_for(node);
+                Ok(Arc::new(NodeClient {
+                    name: node.name.clone(),
+                    send_config: send_config(&config.send_options_for(node), client.commitment()),
+                    client,
+                    concurrency,
+                    permits: Semaphore::new(concurrency),
+                    rebroadcast_permits: Semaphore::new(
+                        concurrency.saturating_sub(config.first_send_reserve(node)),
+                    ),
+                }))
             })
-            .collect();
+            .collect::<Result<_>>()?;
+        let senders = match &config.propagation {
+            None => Senders::All,
+            Some(PropagationConfig::RoundRobin) => Senders::RoundRobin,
+            Some(PropagationConfig::Fixed { node }) => Senders::Fixed(
+                clients
+                    .iter()
+                    .position(|client| &client.name == node)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown sender node {}", node))?,
+            ),
+        };
+
+        let height_client = match config.rebroadcast {
+            Some(_) => {
+                let (node, http_url) = config.http_node()?;
+                Some(Arc::new(http_client(
+                    http_url,
+                    &node.resolved_headers,
+                    CommitmentConfig::confirmed(),
+                )?))
+            }
+            None => None,
+        };
 
-        Self { clients }
+        Ok(Self {
+            clients,
+            senders,
+            fairness: config.fairness,
+            rebroadcast: config.rebroadcast,
+            height_client,
+            event_log,
+            send_tracker,
+        })
+    }
+
+    /// Indices of the nodes the `index`-th transaction is sent to.
+    fn senders(&self, index: usize) -> Vec<usize> {
+        match self.senders {
+            Senders::All => (0..self.clients.len()).collect(),
+            Senders::RoundRobin => vec![index % self.clients.len()],
+            Senders::Fixed(node) => vec![node],
+        }
     }
 
-    // NOTE: In order to send transactions to all clients in parallel, we create threads for each client,
-    //       and each thread will receive a Vec<Transaction> from the main thread and send them to the
-    //       client in parallel.
-    pub fn send_transactions(&self, transactions: &[Transaction]) {
+    /// Send every transaction to every node, or to a single node in propagation mode. Each node
+    /// gets its own task on the current runtime, which keeps up to the node's concurrency of
+    /// `sendTransaction` calls in flight. Sends start in order, but a slow call does not hold up
+    /// the ones after it.
+    ///
+    /// With a `schedule`, the `i`-th transaction is not sent before `schedule[i]` has elapsed
+    /// since sending began, on any node. The fairness settings further hold back each node until
+    /// every node is ready to send a transaction and/or until its turn in a random order.
+    ///
+    /// With rebroadcasting, each transaction is then resent to the node in the background
+    /// without holding up the next one, until it is notified, out of attempts or its blockhash
+    /// per `expiry` has expired. Rebroadcasts count towards the node's concurrency, but leave
+    /// a burst's share of it, and at least one permit, to first sends.
+    pub async fn send_transactions(
+        &self,
+        transactions: &[VersionedTransaction],
+        schedule: Option<&[Duration]>,
+        expiry: &BlockhashExpiry,
+    ) -> Vec<SendRecord> {
         if transactions.is_empty() {
-            return;
+            return Vec::new();
         }
 
-        let mut thread_handles = Vec::with_capacity(self.clients.len());
-        let mut senders = Vec::with_capacity(self.clients.len());
-
-        for client_arc in &self.clients {
-            let (tx, rx) = mpsc::channel::<Vec<Transaction>>();
-            senders.push(tx);
-
-            let current_client_arc = Arc::clone(client_arc);
-            let handle = thread::spawn(move || {
-                let rt = tokio::runtime::Builder::new_current_thread()
-                    .enable_all()
-                    .build()
-                    .unwrap_or_else(|e| {
-                        panic!("Failed to create Tokio runtime for worker thread: {:?}", e)
-                    });
-
-                let transactions = rx.recv().unwrap();
-                for transaction in transactions {
-                    rt.block_on(async {
-                        current_client_arc
-                            .send_transaction(&transaction)
-                            .await
-                            .unwrap_or_else(|e| {
-                                if !e.to_string().contains("processed") {
-                                    panic!("Failed to send transaction via RPC client: {:?}", e);
-                                } else {
-                                    transaction.signatures[0]
+        let transactions: Arc<[VersionedTransaction]> = transactions.into();
+        let schedule: Option<Arc<[Duration]>> = schedule.map(Into::into);
+        let senders: Vec<Vec<usize>> = (0..transactions.len()).map(|i| self.senders(i)).collect();
+        let gates: Arc<[SendGate]> = {
+            let mut rng = rand::rng();
+            senders
+                .iter()
+                .map(|nodes| SendGate::new(nodes.clone(), self.fairness, &mut rng))
+                .collect()
+        };
+        let rebroadcaster = self.rebroadcast.map(|settings| Rebroadcaster {
+            settings,
+            expiry: Arc::new(expiry.clone()),
+            block_height: self.watch_block_height(expiry),
+        });
+        let start = Instant::now();
+        let handles: Vec<_> = self
+            .clients
+            .iter()
+            .enumerate()
+            .map(|(node_index, node)| {
+                let node = Arc::clone(node);
+                let indices: Vec<usize> = (0..transactions.len())
+                    .filter(|&i| senders[i].contains(&node_index))
+                    .collect();
+                let transactions = Arc::clone(&transactions);
+                let event_log = Arc::clone(&self.event_log);
+                let send_tracker = self.send_tracker.clone();
+                let schedule = schedule.clone();
+                let gates = Arc::clone(&gates);
+                let rebroadcaster = rebroadcaster.clone();
+                tokio::spawn(async move {
+                    // Each send owns its handles, so the in-flight futures borrow nothing
+                    let sends = stream::iter(indices)
+                        .map(|i| {
+                            let node = Arc::clone(&node);
+                            let transactions = Arc::clone(&transactions);
+                            let event_log = Arc::clone(&event_log);
+                            let send_tracker = send_tracker.clone();
+                            let gates = Arc::clone(&gates);
+                            let rebroadcaster = rebroadcaster.clone();
+                            let release = schedule
+                                .as_ref()
+                                .and_then(|schedule| schedule.get(i))
+                                .map(|offset| start + *offset);
+                            async move {
+                                if let Some(release) = release {
+                                    tokio::time::sleep_until(release).await;
                                 }
-                            });
-                    });
+                                // Taken before entering the gate, so nodes start sending in
+                                // the gate's order and at its barrier. Never closed.
+                                let permit = node.permits.acquire().await;
+                                let position = gates[i].enter(node_index).await;
+                                let record = send_transaction(
+                                    &node,
+                                    &transactions[i],
+                                    Some(position),
+                                    0,
+                                    &event_log,
+                                    &send_tracker,
+                                )
+                                .await;
+                                drop(permit);
+                                let rebroadcasts = rebroadcaster
+                                    .filter(|rebroadcaster| {
+                                        record.error != Some(SendErrorKind::AlreadyProcessed)
+                                            && !rebroadcaster
+                                                .expired(&transactions[i], record.error)
+                                    })
+                                    .map(|rebroadcaster| {
+                                        tokio::spawn(rebroadcast_transaction(
+                                            node,
+                                            transactions,
+                                            i,
+                                            record.send_start_us,
+                                            rebroadcaster,
+                                            event_log,
+                                            send_tracker,
+                                        ))
+                                    });
+                                (record, rebroadcasts)
+                            }
+                        })
+                        .buffer_unordered(node.concurrency)
+                        .collect::<Vec<_>>()
+                        .await;
+
+                    let mut records = Vec::with_capacity(sends.len());
+                    let mut rebroadcasts = Vec::new();
+                    for (record, handle) in sends {
+                        records.push(record);
+                        rebroadcasts.extend(handle);
+                    }
+                    for handle in rebroadcasts {
+                        records.extend(join(handle).await);
+                    }
+                    records
+                })
+            })
+            .collect();
+
+        let mut records = Vec::with_capacity(transactions.len() * handles.len());
+        for handle in handles {
+            records.extend(join(handle).await);
+        }
+        records
+    }
+
+    /// Latest block height, polled in the background until the receivers are dropped if any
+    /// transaction expires; otherwise it stays 0.
+    fn watch_block_height(&self, expiry: &BlockhashExpiry) -> watch::Receiver<u64> {
+        let (sender, receiver) = watch::channel(0);
+        if let Some(client) = self.height_client.as_ref().filter(|_| !expiry.is_empty()) {
+            let client = Arc::clone(client);
+            tokio::spawn(async move {
+                let mut ticks = tokio::time::interval(BLOCK_HEIGHT_POLL_INTERVAL);
+                ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                loop {
+                    // Stop as soon as every receiver is gone, even while polls keep failing
+                    let polled = tokio::select! {
+                        _ = sender.closed() => break,
+                        polled = async {
+                            ticks.tick().await;
+                            client.get_block_height().await
+                        } => polled,
+                    };
+                    match polled {
+                        Ok(height) => {
+                            if sender.send(height).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => tracing::debug!("Polling the block height failed: {}", e),
+                    }
                 }
             });
-            thread_handles.push(handle);
         }
+        receiver
+    }
+}
 
-        for sender in &senders {
-            sender.send(transactions.to_owned()).unwrap();
-        }
+/// Await a send task, re-raising its panic.
+async fn join<T>(handle: JoinHandle<T>) -> T {
+    handle.await.unwrap_or_else(|e| match e.try_into_panic() {
+        Ok(panic_payload) => std::panic::resume_unwind(panic_payload),
+        Err(e) => panic!("Send task failed: {}", e),
+    })
+}
 
-        drop(senders);
+/// Resend the `index`-th transaction to `node` every interval after its first send started at
+/// `first_send_start_us`, until it is out of attempts, its blockhash has expired, the node
+/// reports it as already processed or, if configured, a monitor was notified of it. A resend
+/// that is held up skips the intervals it missed rather than catching up.
+async fn rebroadcast_transaction(
+    node: Arc<NodeClient>,
+    transactions: Arc<[VersionedTransaction]>,
+    index: usize,
+    first_send_start_us: u64,
+    rebroadcaster: Rebroadcaster,
+    event_log: Arc<EventLog>,
+    send_tracker: SendTracker,
+) -> Vec<SendRecord> {
+    let transaction = &transactions[index];
+    let settings = rebroadcaster.settings;
+    let interval = Duration::from_millis(settings.interval_ms);
+    let first_send = Instant::from_std(clock::instant_at(first_send_start_us));
+    let mut ticks = tokio::time::interval_at(first_send + interval, interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut records = Vec::new();
+    for attempt in 1..settings.max_attempts {
+        ticks.tick().await;
+        if rebroadcaster.expired(transaction, None)
+            || (settings.stop_on_processed && send_tracker.notified(&transaction.signatures[0]))
+        {
+            break;
+        }
+        // Never closed
+        let _rebroadcast_permit = node.rebroadcast_permits.acquire().await;
+        let _permit = node.permits.acquire().await;
+        let record =
+            send_transaction(&node, transaction, None, attempt, &event_log, &send_tracker).await;
+        let done = record.error == Some(SendErrorKind::AlreadyProcessed)
+            || rebroadcaster.expired(transaction, record.error);
+        records.push(record);
+        if done {
+            break;
+        }
+    }
+    records
+}
 
-        for handle in thread_handles {
-            handle.join().unwrap_or_else(|panic_payload| {
-                std::panic::resume_unwind(panic_payload);
-            });
+/// Send one transaction to one node, recording its timing, `position` in the send order (first
+/// sends only) and `attempt`. The caller holds one of the node's permits.
+async fn send_transaction(
+    node: &NodeClient,
+    transaction: &VersionedTransaction,
+    position: Option<usize>,
+    attempt: usize,
+    event_log: &EventLog,
+    send_tracker: &SendTracker,
+) -> SendRecord {
+    let signature = transaction.signatures[0].to_string();
+    let send_start_us = clock::now_us();
+    send_tracker.record(transaction.signatures[0], send_start_us);
+    event_log.record_at(
+        send_start_us,
+        Event::SendStarted {
+            signature: signature.clone(),
+            node: node.name.clone(),
+            position,
+            attempt,
+        },
+    );
+    let (error, error_kind) = match node
+        .client
+        .send_transaction_with_config(transaction, node.send_config)
+        .await
+    {
+        Ok(_) => (None, None),
+        Err(e) => {
+            let kind = SendErrorKind::classify(&e);
+            tracing::debug!(
+                "Sending {} to {} failed ({}): {}",
+                signature,
+                node.name,
+                kind,
+                e
+            );
+            (Some(e.to_string()), Some(kind))
         }
+    };
+    let send_ack_us = clock::now_us();
+    event_log.record_at(
+        send_ack_us,
+        Event::SendCompleted {
+            signature: signature.clone(),
+            node: node.name.clone(),
+            error,
+            error_kind,
+        },
+    );
+    SendRecord {
+        signature,
+        node_name: node.name.clone(),
+        send_start_us,
+        send_ack_us,
+        send_position: position,
+        attempt,
+        error: error_kind,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_config_defaults_preflight_to_the_client_commitment() {
+        let config = send_config(&SendOptions::default(), CommitmentConfig::finalized());
+        assert_eq!(
+            config.preflight_commitment,
+            Some(CommitmentLevel::Finalized)
+        );
+        assert!(!config.skip_preflight);
+        assert_eq!(config.encoding, None);
+
+        let options = SendOptions {
+            preflight_commitment: Some(Commitment::Processed),
+            ..SendOptions::default()
+        };
+        let config = send_config(&options, CommitmentConfig::finalized());
+        assert_eq!(
+            config.preflight_commitment,
+            Some(CommitmentLevel::Processed)
+        );
+    }
+
+    #[test]
+    fn rebroadcasts_stop_once_a_blockhash_expires_or_a_nonce_is_gone() {
+        use solana_sdk::{hash::Hash, message::Message, pubkey::Pubkey, transaction::Transaction};
+
+        let transaction = |blockhash: &Hash| -> VersionedTransaction {
+            let message = Message::new_with_blockhash(&[], Some(&Pubkey::new_unique()), blockhash);
+            Transaction::new_unsigned(message).into()
+        };
+        let recent = Hash::new_unique();
+        let (height, block_height) = watch::channel(0);
+        let rebroadcaster = Rebroadcaster {
+            settings: RebroadcastConfig {
+                interval_ms: 400,
+                max_attempts: 10,
+                stop_on_processed: true,
+            },
+            expiry: Arc::new(BlockhashExpiry::from([(recent, 150)])),
+            block_height,
+        };
+
+        let recent = transaction(&recent);
+        assert!(!rebroadcaster.expired(&recent, None));
+        assert!(!rebroadcaster.expired(&recent, Some(SendErrorKind::Timeout)));
+        assert!(rebroadcaster.expired(&recent, Some(SendErrorKind::BlockhashNotFound)));
+        height.send(150).unwrap();
+        assert!(!rebroadcaster.expired(&recent, None));
+        height.send(151).unwrap();
+        assert!(rebroadcaster.expired(&recent, None));
+
+        // A durable nonce is not in the expiry map, and is consumed or advanced once not found
+        let nonce = transaction(&Hash::new_unique());
+        assert!(!rebroadcaster.expired(&nonce, None));
+        assert!(!rebroadcaster.expired(&nonce, Some(SendErrorKind::Timeout)));
+        assert!(rebroadcaster.expired(&nonce, Some(SendErrorKind::BlockhashNotFound)));
     }
 }
@@ -0,0 +1,227 @@
+use crate::config::{parse_pubkey, BenchmarkConfig, TemplateConfig};
+use anyhow::{Context, Result};
+use base64::Engine;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+/// The instructions a benchmark transaction carries, besides compute budget and nonce
+/// instructions added by `TransactionBuilder`.
+pub trait TransactionTemplate: Send + Sync {
+    /// Instructions of the `index`-th transaction of the batch, paid and signed by `payer`.
+    /// They must differ between indices so every transaction gets a distinct signature.
+    fn instructions(&self, payer: &Pubkey, index: usize) -> Result<Vec<Instruction>>;
+
+    /// Lamports the payer transfers away in the `index`-th transaction, for cost estimates.
+    fn lamports(&self, _index: usize) -> u64 {
+        0
+    }
+}
+
+/// Recipient of the `index`-th transaction, round-robin.
+fn recipient(recipients: &[Pubkey], index: usize) -> &Pubkey {
+    &recipients[index % recipients.len()]
+}
+
+/// `amount_lamports + index` lamports to the next recipient.
+pub struct SolTransfer {
+    recipients: Vec<Pubkey>,
+    amount_lamports: u64,
+}
+
+impl TransactionTemplate for SolTransfer {
+    fn instructions(&self, payer: &Pubkey, index: usize) -> Result<Vec<Instruction>> {
+        Ok(vec![system_instruction::transfer(
+            payer,
+            recipient(&self.recipients, index),
+            self.lamports(index),
+        )])
+    }
+
+    fn lamports(&self, index: usize) -> u64 {
+        self.amount_lamports + index as u64
+    }
+}
+
+/// A single memo signed by the payer.
+pub struct Memo {
+    message: String,
+}
+
+impl TransactionTemplate for Memo {
+    fn instructions(&self, payer: &Pubkey, index: usize) -> Result<Vec<Instruction>> {
+        let memo = format!("{} #{}", self.message, index);
+        Ok(vec![spl_memo::build_memo(memo.as_bytes(), &[payer])])
+    }
+}
+
+/// `amount + index` base units of `mint` from the payer's associated token account to the
+/// next recipient's.
+pub struct SplTransfer {
+    mint: Pubkey,
+    recipients: Vec<Pubkey>,
+    decimals: u8,
+    amount: u64,
+}
+
+impl TransactionTemplate for SplTransfer {
+    fn instructions(&self, payer: &Pubkey, index: usize) -> Result<Vec<Instruction>> {
+        let source = get_associated_token_address(payer, &self.mint);
+        let destination =
+            get_associated_token_address(recipient(&self.recipients, index), &self.mint);
+        let transfer = spl_token::instruction::transfer_checked(
+            &spl_token::id(),
+            &source,
+            &self.mint,
+            &destination,
+            payer,
+            &[],
+            self.amount + index as u64,
+            self.decimals,
+        )?;
+        Ok(vec![transfer])
+    }
+}
+
+/// A fixed instruction described in the config, optionally followed by a memo carrying the
+/// index to keep signatures distinct.
+pub struct ProgramInvoke {
+    instruction: Instruction,
+    index_memo: bool,
+}
+
+impl TransactionTemplate for ProgramInvoke {
+    fn instructions(&self, payer: &Pubkey, index: usize) -> Result<Vec<Instruction>> {
+        let mut instructions = vec![self.instruction.clone()];
+        if self.index_memo {
+            let memo = format!("usopp #{}", index);
+            instructions.push(spl_memo::build_memo(memo.as_bytes(), &[payer]));
+        }
+        Ok(instructions)
+    }
+}
+
+/// Build the template selected in `config` for transactions paid by `payers`.
+pub fn from_config(
+    config: &BenchmarkConfig,
+    payers: &[Keypair],
+) -> Result<Box<dyn TransactionTemplate>> {
+    let recipients = || -> Result<Vec<Pubkey>> {
+        if config.recipients.is_empty() {
+            return Ok(vec![parse_pubkey("recipient", &config.recipient)?]);
+        }
+        config
+            .recipients
+            .iter()
+            .map(|recipient| parse_pubkey("recipient", recipient))
+            .collect()
+    };
+    Ok(match &config.template {
+        TemplateConfig::SolTransfer => Box::new(SolTransfer {
+            recipients: recipients()?,
+            amount_lamports: config.amount_lamports,
+        }),
+        TemplateConfig::Memo { message } => Box::new(Memo {
+            message: message.clone(),
+        }),
+        TemplateConfig::SplTransfer {
+            mint,
+            decimals,
+            amount,
+        } => Box::new(SplTransfer {
+            mint: parse_pubkey("mint", mint)?,
+            recipients: recipients()?,
+            decimals: *decimals,
+            amount: *amount,
+        }),
+        TemplateConfig::ProgramInvoke {
+            program_id,
+            accounts,
+            data,
+            index_memo,
+        } => {
+            // Without the memo only the payer or a durable nonce tells transactions apart
+            if !index_memo
+                && config.durable_nonce.is_none()
+                && config.num_transactions > payers.len()
+            {
+                anyhow::bail!(
+                    "`program_invoke` transactions are identical apart from their payer, so {} \
+                     transactions need as many payers or `durable_nonce`; or set `index_memo`",
+                    config.num_transactions
+                );
+            }
+            let accounts = accounts
+                .iter()
+                .map(|account| {
+                    let pubkey = parse_pubkey("account", &account.pubkey)?;
+                    // The payer is the only keypair available to sign, so a fixed signer only
+                    // works with a single payer
+                    if account.is_signer && (payers.len() != 1 || pubkey != payers[0].pubkey()) {
+                        anyhow::bail!(
+                            "Account {} is marked as signer, but only a single payer can sign",
+                            pubkey
+                        );
+                    }
+                    Ok(AccountMeta {
+                        pubkey,
+                        is_signer: account.is_signer,
+                        is_writable: account.is_writable,
+                    })
+                })
+                .collect::<Result<_>>()?;
+            let data = base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .context("Invalid base64 instruction data")?;
+            Box::new(ProgramInvoke {
+                instruction: Instruction {
+                    program_id: parse_pubkey("program id", program_id)?,
+                    accounts,
+                    data,
+                },
+                index_memo: *index_memo,
+            })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program_invoke(index_memo: bool) -> ProgramInvoke {
+        ProgramInvoke {
+            instruction: Instruction {
+                program_id: Pubkey::new_unique(),
+                accounts: Vec::new(),
+                data: vec![1, 2, 3],
+            },
+            index_memo,
+        }
+    }
+
+    #[test]
+    fn program_invoke_sends_only_the_configured_instruction_by_default() {
+        let template = program_invoke(false);
+        let instructions = template.instructions(&Pubkey::new_unique(), 7).unwrap();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0], template.instruction);
+    }
+
+    #[test]
+    fn program_invoke_index_memo_is_appended_when_enabled() {
+        let payer = Pubkey::new_unique();
+        let template = program_invoke(true);
+        let instructions = template.instructions(&payer, 7).unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0], template.instruction);
+        assert_eq!(
+            instructions[1],
+            spl_memo::build_memo(b"usopp #7", &[&payer])
+        );
+    }
+}
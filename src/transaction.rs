@@ -1,52 +1,449 @@
+use crate::config::BlockhashRefresh;
+use crate::nonce;
+use crate::template::TransactionTemplate;
 use anyhow::Result;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
-    commitment_config::CommitmentConfig,
-    message::Message,
+    address_lookup_table::{state::AddressLookupTable, AddressLookupTableAccount},
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    message::{v0, Message, VersionedMessage},
     pubkey::Pubkey,
     signature::{Keypair, Signer},
     system_instruction,
-    transaction::Transaction,
+    transaction::{Transaction, VersionedTransaction},
 };
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Transactions signed between two blockhash refresh checks.
+pub const BUILD_CHUNK_SIZE: usize = 1_000;
+
+/// Per-transaction inputs of a batch build.
+#[derive(Debug, Clone, Copy)]
+pub struct TransactionParams {
+    /// Position in the batch, passed to the template.
+    pub index: usize,
+    /// Priority fee in micro-lamports per compute unit.
+    pub compute_unit_price: Option<u64>,
+}
+
+/// Timing of a batch build.
+#[derive(Debug, Clone, Copy)]
+pub struct BuildStats {
+    pub transactions: usize,
+    pub elapsed: Duration,
+    pub blockhash_fetches: usize,
+    /// `getMultipleAccounts` calls reading the nonce accounts of durable nonce transactions.
+    pub nonce_fetches: usize,
+}
+
+impl BuildStats {
+    pub fn transactions_per_second(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            0.0
+        } else {
+            self.transactions as f64 / secs
+        }
+    }
+}
+
+/// Last block height at which each recent blockhash of a batch is valid. Durable nonces have no
+/// entry, since they do not expire.
+pub type BlockhashExpiry = HashMap<Hash, u64>;
+
+/// Where transactions get their `recent_blockhash` from.
+#[derive(Debug, Clone)]
+pub enum BlockhashSource {
+    /// A recent blockhash, refetched according to the refresh policy.
+    Recent(BlockhashRefresh),
+    /// One durable nonce account per transaction, advanced with the payer as nonce authority.
+    /// Transactions built this way do not expire.
+    DurableNonce(Vec<Pubkey>),
+}
+
+/// Message format of the built transactions.
+#[derive(Debug, Clone)]
+pub enum TransactionFormat {
+    Legacy,
+    /// v0 message, compiled against the given address lookup tables.
+    V0 {
+        lookup_tables: Vec<Pubkey>,
+    },
+}
+
+/// A transaction ready to be signed.
+struct SigningJob<'a> {
+    params: &'a TransactionParams,
+    blockhash: Hash,
+    nonce_account: Option<Pubkey>,
+}
+
+/// A blockhash together with what is needed to decide when it is too old.
+struct FetchedBlockhash {
+    hash: Hash,
+    /// Only fetched when `BlockhashRefresh::max_slot_age` is set.
+    slot: Option<u64>,
+    fetched_at: Instant,
+    last_valid_block_height: u64,
+}
 
 pub struct TransactionBuilder {
     rpc_client: RpcClient,
-    from_keypair: Keypair,
-    to_pubkey: Pubkey,
-    amount_lamports: u64,
+    /// Transaction `i` is paid and signed by `payers[i % payers.len()]`. The first payer is
+    /// also the durable nonce authority.
+    payers: Vec<Keypair>,
+    template: Box<dyn TransactionTemplate>,
+    blockhash_source: BlockhashSource,
+    format: TransactionFormat,
+    compute_unit_limit: Option<u32>,
 }
 
 impl TransactionBuilder {
+    /// Builder fetching blockhashes, nonces and lookup tables through `rpc_client`.
     pub fn new(
-        rpc_url: String,
-        from_keypair: Keypair,
-        to_pubkey: Pubkey,
-        amount_lamports: u64,
+        rpc_client: RpcClient,
+        payers: Vec<Keypair>,
+        template: Box<dyn TransactionTemplate>,
+        blockhash_source: BlockhashSource,
+        format: TransactionFormat,
+        compute_unit_limit: Option<u32>,
     ) -> Self {
         Self {
-            rpc_client: RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed()),
-            from_keypair,
-            to_pubkey,
-            amount_lamports,
+            rpc_client,
+            payers,
+            template,
+            blockhash_source,
+            format,
+            compute_unit_limit,
         }
     }
 
-    pub async fn build_transaction(&self) -> Result<Transaction> {
-        // Get recent blockhash
-        let recent_blockhash = self.rpc_client.get_latest_blockhash().await?;
+    /// Build one transaction per entry of `params` and sign them in parallel. With a recent
+    /// blockhash, transactions share it until the refresh policy asks for a new one; with
+    /// durable nonces, each transaction uses its own nonce account. Also returns when the recent
+    /// blockhashes expire.
+    pub async fn build_batch(
+        &self,
+        params: &[TransactionParams],
+    ) -> Result<(Vec<VersionedTransaction>, BuildStats, BlockhashExpiry)> {
+        let started = Instant::now();
+        let lookup_tables = match &self.format {
+            TransactionFormat::Legacy => Vec::new(),
+            TransactionFormat::V0 { lookup_tables } => {
+                self.fetch_lookup_tables(lookup_tables).await?
+            }
+        };
+        let mut expiry = BlockhashExpiry::new();
+        let (transactions, blockhash_fetches, nonce_fetches) = match &self.blockhash_source {
+            BlockhashSource::Recent(refresh) => {
+                let (transactions, blockhash_fetches) = self
+                    .build_with_recent(params, refresh, &lookup_tables, &mut expiry)
+                    .await?;
+                (transactions, blockhash_fetches, 0)
+            }
+            BlockhashSource::DurableNonce(accounts) => {
+                let (transactions, nonce_fetches) = self
+                    .build_with_nonces(params, accounts, &lookup_tables)
+                    .await?;
+                (transactions, 0, nonce_fetches)
+            }
+        };
+
+        let stats = BuildStats {
+            transactions: transactions.len(),
+            elapsed: started.elapsed(),
+            blockhash_fetches,
+            nonce_fetches,
+        };
+        Ok((transactions, stats, expiry))
+    }
+
+    async fn build_with_recent(
+        &self,
+        params: &[TransactionParams],
+        refresh: &BlockhashRefresh,
+        lookup_tables: &[AddressLookupTableAccount],
+        expiry: &mut BlockhashExpiry,
+    ) -> Result<(Vec<VersionedTransaction>, usize)> {
+        let mut transactions = Vec::with_capacity(params.len());
+        let mut blockhash: Option<FetchedBlockhash> = None;
+        let mut blockhash_fetches = 0;
+
+        for chunk in params.chunks(BUILD_CHUNK_SIZE) {
+            let stale = match &blockhash {
+                Some(current) => self.is_stale(current, refresh).await?,
+                None => true,
+            };
+            if stale {
+                let fetched = self.fetch_blockhash(refresh).await?;
+                expiry.insert(fetched.hash, fetched.last_valid_block_height);
+                blockhash = Some(fetched);
+                blockhash_fetches += 1;
+            }
+            let hash = blockhash.as_ref().map(|b| b.hash).unwrap_or_default();
+            let jobs: Vec<SigningJob> = chunk
+                .iter()
+                .map(|params| SigningJob {
+                    params,
+                    blockhash: hash,
+                    nonce_account: None,
+                })
+                .collect();
+            transactions.extend(tokio::task::block_in_place(|| {
+                self.sign_parallel(&jobs, lookup_tables)
+            })?);
+        }
+        Ok((transactions, blockhash_fetches))
+    }
+
+    async fn build_with_nonces(
+        &self,
+        params: &[TransactionParams],
+        accounts: &[Pubkey],
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> Result<(Vec<VersionedTransaction>, usize)> {
+        // A nonce is consumed by the first transaction that lands with it
+        if params.len() > accounts.len() {
+            anyhow::bail!(
+                "{} transactions need as many durable nonce accounts, but only {} are configured",
+                params.len(),
+                accounts.len()
+            );
+        }
+        let accounts = &accounts[..params.len()];
+        let (nonces, nonce_fetches) =
+            nonce::fetch_nonces(&self.rpc_client, accounts, &self.nonce_authority().pubkey())
+                .await?;
+
+        let jobs: Vec<SigningJob> = params
+            .iter()
+            .zip(accounts.iter().zip(nonces))
+            .map(|(params, (account, nonce))| SigningJob {
+                params,
+                blockhash: nonce,
+                nonce_account: Some(*account),
+            })
+            .collect();
+        let transactions =
+            tokio::task::block_in_place(|| self.sign_parallel(&jobs, lookup_tables))?;
+        Ok((transactions, nonce_fetches))
+    }
+
+    pub fn template(&self) -> &dyn TransactionTemplate {
+        self.template.as_ref()
+    }
 
-        // Create transfer instruction
-        let transfer_instruction = system_instruction::transfer(
-            &self.from_keypair.pubkey(),
-            &self.to_pubkey,
-            self.amount_lamports,
+    /// Payer of the `index`-th transaction.
+    pub fn payer(&self, index: usize) -> &Keypair {
+        &self.payers[index % self.payers.len()]
+    }
+
+    fn nonce_authority(&self) -> &Keypair {
+        &self.payers[0]
+    }
+
+    async fn fetch_lookup_tables(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<AddressLookupTableAccount>> {
+        let mut tables = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let account = self.rpc_client.get_account(address).await?;
+            let table = AddressLookupTable::deserialize(&account.data)
+                .map_err(|e| anyhow::anyhow!("Invalid address lookup table {}: {}", address, e))?;
+            tables.push(AddressLookupTableAccount {
+                key: *address,
+                addresses: table.addresses.to_vec(),
+            });
+        }
+        Ok(tables)
+    }
+
+    async fn fetch_blockhash(&self, refresh: &BlockhashRefresh) -> Result<FetchedBlockhash> {
+        let (hash, last_valid_block_height) = self
+            .rpc_client
+            .get_latest_blockhash_with_commitment(self.rpc_client.commitment())
+            .await?;
+        let slot = match refresh.max_slot_age {
+            Some(_) => Some(self.rpc_client.get_slot().await?),
+            None => None,
+        };
+        tracing::debug!(
+            "Fetched blockhash {} (slot {:?}, valid through block height {})",
+            hash,
+            slot,
+            last_valid_block_height
         );
+        Ok(FetchedBlockhash {
+            hash,
+            slot,
+            fetched_at: Instant::now(),
+            last_valid_block_height,
+        })
+    }
+
+    async fn is_stale(
+        &self,
+        blockhash: &FetchedBlockhash,
+        refresh: &BlockhashRefresh,
+    ) -> Result<bool> {
+        if let Some(interval_ms) = refresh.interval_ms {
+            if blockhash.fetched_at.elapsed() >= Duration::from_millis(interval_ms) {
+                return Ok(true);
+            }
+        }
+        if let (Some(max_slot_age), Some(slot)) = (refresh.max_slot_age, blockhash.slot) {
+            let current_slot = self.rpc_client.get_slot().await?;
+            return Ok(current_slot.saturating_sub(slot) >= max_slot_age);
+        }
+        Ok(false)
+    }
+
+    fn sign_parallel(
+        &self,
+        jobs: &[SigningJob],
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> Result<Vec<VersionedTransaction>> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let per_thread = jobs.len().div_ceil(threads).max(1);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = jobs
+                .chunks(per_thread)
+                .map(|part| {
+                    scope.spawn(move || {
+                        part.iter()
+                            .map(|job| self.sign_transaction(job, lookup_tables))
+                            .collect::<Result<Vec<_>>>()
+                    })
+                })
+                .collect();
+            let mut transactions = Vec::with_capacity(jobs.len());
+            for handle in handles {
+                transactions.extend(handle.join().expect("Signing thread panicked")?);
+            }
+            Ok(transactions)
+        })
+    }
+
+    fn sign_transaction(
+        &self,
+        job: &SigningJob,
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> Result<VersionedTransaction> {
+        let params = job.params;
+        let payer_keypair = self.payer(params.index);
+        let payer = payer_keypair.pubkey();
+        let mut signers = vec![payer_keypair];
+        let mut instructions = Vec::with_capacity(4);
+        // A durable nonce transaction must advance the nonce in its first instruction
+        if let Some(nonce_account) = &job.nonce_account {
+            let authority = self.nonce_authority();
+            instructions.push(system_instruction::advance_nonce_account(
+                nonce_account,
+                &authority.pubkey(),
+            ));
+            if authority.pubkey() != payer {
+                signers.push(authority);
+            }
+        }
+        // Compute budget instructions
+        if let Some(units) = self.compute_unit_limit {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(units));
+        }
+        if let Some(micro_lamports) = params.compute_unit_price {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+                micro_lamports,
+            ));
+        }
+
+        // Template instructions
+        instructions.extend(self.template.instructions(&payer, params.index)?);
 
         // Build and sign transaction
-        let message = Message::new(&[transfer_instruction], Some(&self.from_keypair.pubkey()));
-        let mut transaction = Transaction::new_unsigned(message);
-        transaction.sign(&[&self.from_keypair], recent_blockhash);
+        match &self.format {
+            TransactionFormat::Legacy => {
+                let message = Message::new(&instructions, Some(&payer));
+                let mut transaction = Transaction::new_unsigned(message);
+                transaction.try_sign(&signers, job.blockhash)?;
+                Ok(transaction.into())
+            }
+            TransactionFormat::V0 { .. } => {
+                let lookup_tables = without_nonce_account(lookup_tables, job.nonce_account);
+                let message =
+                    v0::Message::try_compile(&payer, &instructions, &lookup_tables, job.blockhash)?;
+                Ok(VersionedTransaction::try_new(
+                    VersionedMessage::V0(message),
+                    &signers,
+                )?)
+            }
+        }
+    }
+}
 
-        Ok(transaction)
+/// `lookup_tables` without `nonce_account`, which must be a static account key: the runtime only
+/// treats a transaction as durable nonce if its nonce account is not loaded from a table.
+fn without_nonce_account(
+    lookup_tables: &[AddressLookupTableAccount],
+    nonce_account: Option<Pubkey>,
+) -> Cow<'_, [AddressLookupTableAccount]> {
+    match nonce_account {
+        Some(nonce_account)
+            if lookup_tables
+                .iter()
+                .any(|table| table.addresses.contains(&nonce_account)) =>
+        {
+            Cow::Owned(
+                lookup_tables
+                    .iter()
+                    .map(|table| AddressLookupTableAccount {
+                        key: table.key,
+                        addresses: table
+                            .addresses
+                            .iter()
+                            .copied()
+                            .filter(|address| *address != nonce_account)
+                            .collect(),
+                    })
+                    .collect(),
+            )
+        }
+        _ => Cow::Borrowed(lookup_tables),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nonce_accounts_are_never_loaded_from_a_lookup_table() {
+        let payer = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let lookup_tables = [AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: vec![nonce_account, recipient],
+        }];
+        let instructions = [
+            system_instruction::advance_nonce_account(&nonce_account, &payer),
+            system_instruction::transfer(&payer, &recipient, 1),
+        ];
+
+        let message = v0::Message::try_compile(
+            &payer,
+            &instructions,
+            &without_nonce_account(&lookup_tables, Some(nonce_account)),
+            Hash::new_unique(),
+        )
+        .unwrap();
+        assert!(message.account_keys.contains(&nonce_account));
+        assert!(!message.account_keys.contains(&recipient));
+        assert_eq!(
+            without_nonce_account(&lookup_tables, None)[0].addresses,
+            lookup_tables[0].addresses
+        );
     }
 }
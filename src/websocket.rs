@@ -1,10 +1,20 @@
-use anyhow::Result;
+use crate::clock;
+use crate::config::{Commitment, ReconnectConfig};
+use crate::events::{Event, EventLog};
+use crate::rpc::SendTracker;
+use anyhow::{Context, Result};
 use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use solana_client::client_error::reqwest;
 use solana_sdk::signature::Signature;
+use solana_sdk::transaction::TransactionError;
 use std::collections::{HashMap, HashSet};
-use std::time::SystemTime;
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::{HeaderMap, HeaderName, HeaderValue};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
 
 // Sent to the server to subscribe
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,36 +66,399 @@ struct SignatureNotification {
 #[derive(Debug, Clone)]
 pub struct ConfirmationResult {
     pub signature: String,
+    pub commitment: Commitment,
     pub timestamp_us: u64,
-    // pub slot: u64,
-    // pub confirmation_status: Option<String>,
+    /// Received on a subscription re-established after a reconnect, so the notification may
+    /// have been delayed by the disconnect.
+    pub resubscribed: bool,
+    /// Slot the notification was delivered for.
+    pub slot: u64,
+    /// Error the transaction failed with; `None` if it succeeded.
+    pub err: Option<String>,
+}
+
+/// Describe the `err` of a signature notification; `None` means the transaction succeeded.
+/// Errors that do not parse as a `TransactionError` are kept as raw JSON.
+pub fn describe_transaction_error(err: Option<&serde_json::Value>) -> Option<String> {
+    let err = err.filter(|value| !value.is_null())?;
+    Some(
+        serde_json::from_value::<TransactionError>(err.clone())
+            .map(|e| e.to_string())
+            .unwrap_or_else(|_| err.to_string()),
+    )
+}
+
+/// How often pending signatures are checked against their deadlines.
+const DEADLINE_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Why a signature never received a notification at some commitment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UnconfirmedReason {
+    /// No notification within `signature_timeout_ms` of the signature first being sent.
+    SignatureTimeout { after_ms: u64 },
+    /// Overall monitoring deadline (`monitor_timeout_ms`) reached.
+    MonitorTimeout { after_ms: u64 },
+    /// The WebSocket connection ended (and could not be re-established) while the signature
+    /// was still pending.
+    ConnectionClosed,
+    /// The node's monitor failed, so none of its signatures were watched.
+    MonitorFailed,
+}
+
+impl std::fmt::Display for UnconfirmedReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnconfirmedReason::SignatureTimeout { after_ms } => {
+                write!(f, "signature timeout ({} ms)", after_ms)
+            }
+            UnconfirmedReason::MonitorTimeout { after_ms } => {
+                write!(f, "monitor timeout ({} ms)", after_ms)
+            }
+            UnconfirmedReason::ConnectionClosed => write!(f, "connection closed"),
+            UnconfirmedReason::MonitorFailed => write!(f, "monitor failed"),
+        }
+    }
+}
+
+/// A (signature, commitment) pair that monitoring gave up on.
+#[derive(Debug, Clone)]
+pub struct UnconfirmedSignature {
+    pub signature: String,
+    pub commitment: Commitment,
+    pub reason: UnconfirmedReason,
+}
+
+/// A lost WebSocket connection and, if it succeeded, the reconnect that followed.
+#[derive(Debug, Clone)]
+pub struct Reconnect {
+    pub disconnected_us: u64,
+    /// `None` if every reconnect attempt failed.
+    pub reconnected_us: Option<u64>,
+}
+
+/// Everything one node's WebSocket monitor observed.
+#[derive(Debug, Clone, Default)]
+pub struct MonitorResult {
+    pub confirmations: Vec<ConfirmationResult>,
+    pub unconfirmed: Vec<UnconfirmedSignature>,
+    pub reconnects: Vec<Reconnect>,
+}
+
+impl MonitorResult {
+    /// Result of a node whose monitor failed: every signature is unconfirmed at every
+    /// commitment, so the node is still ranked and charged for the misses.
+    pub fn failed(signatures: &[String], commitments: &[Commitment]) -> Self {
+        Self {
+            unconfirmed: signatures
+                .iter()
+                .flat_map(|signature| {
+                    commitments.iter().map(|&commitment| UnconfirmedSignature {
+                        signature: signature.clone(),
+                        commitment,
+                        reason: UnconfirmedReason::MonitorFailed,
+                    })
+                })
+                .collect(),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MonitorSettings {
+    pub commitments: Vec<Commitment>,
+    pub signature_timeout: Duration,
+    pub monitor_timeout: Option<Duration>,
+    pub reconnect: ReconnectConfig,
+}
+
+/// Why the message loop of a single connection ended.
+enum ConnectionEnd {
+    /// Every signature reached a terminal state.
+    Completed,
+    /// The connection was closed or errored with signatures still pending.
+    Lost(String),
+}
+
+/// Monitoring progress that survives reconnects.
+struct MonitorState {
+    started_us: u64,
+    pending_notifications: HashSet<(Signature, Commitment)>,
+    result: MonitorResult,
 }
 
 pub struct WebSocketHandle {
+    node_name: String,
     ws_url: String,
+    /// Sent with every handshake, including reconnects.
+    headers: HeaderMap,
     signatures: Vec<Signature>,
+    settings: MonitorSettings,
+    event_log: Arc<EventLog>,
+    send_tracker: SendTracker,
 }
 
 impl WebSocketHandle {
-    pub fn new(ws_url: String, signatures: Vec<Signature>) -> Self {
-        Self { ws_url, signatures }
+    /// Monitor of `ws_url`, sending the node's resolved `headers` with every handshake.
+    pub fn new(
+        node_name: String,
+        ws_url: String,
+        headers: &reqwest::header::HeaderMap,
+        signatures: Vec<Signature>,
+        settings: MonitorSettings,
+        event_log: Arc<EventLog>,
+        send_tracker: SendTracker,
+    ) -> Result<Self> {
+        // The HTTP client's header types come from an older `http` release than the WebSocket
+        // client's, so the already validated headers are carried over by their bytes
+        let mut handshake_headers = HeaderMap::with_capacity(headers.len());
+        for (name, value) in headers {
+            let mut handshake_value = HeaderValue::from_bytes(value.as_bytes())
+                .with_context(|| format!("Invalid value of header {}", name))?;
+            handshake_value.set_sensitive(value.is_sensitive());
+            handshake_headers.insert(
+                HeaderName::from_bytes(name.as_str().as_bytes())
+                    .with_context(|| format!("Invalid header name {}", name))?,
+                handshake_value,
+            );
+        }
+        Ok(Self {
+            node_name,
+            ws_url,
+            headers: handshake_headers,
+            signatures,
+            settings,
+            event_log,
+            send_tracker,
+        })
+    }
+
+    /// Open a connection to the node, with its headers on the handshake request.
+    async fn connect(&self) -> Result<WsStream> {
+        let mut request = self.ws_url.as_str().into_client_request()?;
+        request.headers_mut().extend(self.headers.clone());
+        let (stream, _) = connect_async(request).await?;
+        Ok(stream)
+    }
+
+    /// Give up on `(signature, commitment)` for `reason`.
+    fn mark_unconfirmed(
+        &self,
+        signature: Signature,
+        commitment: Commitment,
+        reason: UnconfirmedReason,
+        unconfirmed: &mut Vec<UnconfirmedSignature>,
+    ) {
+        tracing::warn!(
+            "Signature {} not confirmed at {} on {}: {}",
+            signature,
+            commitment,
+            self.ws_url,
+            reason
+        );
+        self.event_log.record(Event::SignatureUnconfirmed {
+            signature: signature.to_string(),
+            node: self.node_name.clone(),
+            commitment,
+            reason: reason.clone(),
+        });
+        unconfirmed.push(UnconfirmedSignature {
+            signature: signature.to_string(),
+            commitment,
+            reason,
+        });
+    }
+
+    /// Drop pending signatures whose per-signature or overall deadline has passed.
+    fn expire_pending(&self, state: &mut MonitorState) {
+        let now_us = clock::now_us();
+        let monitor_expired = self.settings.monitor_timeout.filter(|timeout| {
+            now_us.saturating_sub(state.started_us) >= timeout.as_micros() as u64
+        });
+        let signature_timeout_us = self.settings.signature_timeout.as_micros() as u64;
+
+        let expired: Vec<((Signature, Commitment), UnconfirmedReason)> = state
+            .pending_notifications
+            .iter()
+            .filter_map(|&(signature, commitment)| {
+                if let Some(timeout) = monitor_expired {
+                    return Some((
+                        (signature, commitment),
+                        UnconfirmedReason::MonitorTimeout {
+                            after_ms: timeout.as_millis() as u64,
+                        },
+                    ));
+                }
+                // The per-signature clock only starts once the transaction has been sent.
+                let sent_us = self.send_tracker.first_sent_us(&signature)?;
+                (now_us.saturating_sub(sent_us) >= signature_timeout_us).then_some((
+                    (signature, commitment),
+                    UnconfirmedReason::SignatureTimeout {
+                        after_ms: self.settings.signature_timeout.as_millis() as u64,
+                    },
+                ))
+            })
+            .collect();
+
+        for ((signature, commitment), reason) in expired {
+            state.pending_notifications.remove(&(signature, commitment));
+            self.mark_unconfirmed(signature, commitment, reason, &mut state.result.unconfirmed);
+        }
+    }
+
+    pub async fn monitor_confirmation(&self) -> Result<MonitorResult> {
+        // The initial connection failing is fatal for this node.
+        let mut ws_stream = self.connect().await?;
+
+        // One subscription per (signature, commitment): each delivers a single notification.
+        let mut state = MonitorState {
+            started_us: clock::now_us(),
+            pending_notifications: self
+                .signatures
+                .iter()
+                .flat_map(|signature| {
+                    self.settings
+                        .commitments
+                        .iter()
+                        .map(move |commitment| (*signature, *commitment))
+                })
+                .collect(),
+            result: MonitorResult::default(),
+        };
+        let mut resubscribed = false;
+
+        loop {
+            let reason = match self
+                .run_connection(ws_stream, resubscribed, &mut state)
+                .await?
+            {
+                ConnectionEnd::Completed => break,
+                ConnectionEnd::Lost(reason) => reason,
+            };
+
+            let disconnected_us = clock::now_us();
+            self.event_log.record_at(
+                disconnected_us,
+                Event::WebSocketDisconnected {
+                    node: self.node_name.clone(),
+                    reason,
+                    pending: state.pending_notifications.len(),
+                },
+            );
+            state.result.reconnects.push(Reconnect {
+                disconnected_us,
+                reconnected_us: None,
+            });
+
+            match self.reconnect(&mut state).await {
+                Some(stream) => {
+                    let reconnected_us = clock::now_us();
+                    if let Some(reconnect) = state.result.reconnects.last_mut() {
+                        reconnect.reconnected_us = Some(reconnected_us);
+                    }
+                    self.event_log.record_at(
+                        reconnected_us,
+                        Event::WebSocketReconnected {
+                            node: self.node_name.clone(),
+                            gap_us: reconnected_us.saturating_sub(disconnected_us),
+                        },
+                    );
+                    ws_stream = stream;
+                    resubscribed = true;
+                }
+                None => break,
+            }
+        }
+
+        if !state.pending_notifications.is_empty() {
+            tracing::warn!(
+                "WebSocket {} finished monitoring with {} pending signatures: {:?}",
+                self.ws_url,
+                state.pending_notifications.len(),
+                state.pending_notifications
+            );
+            for (signature, commitment) in state.pending_notifications.drain() {
+                self.mark_unconfirmed(
+                    signature,
+                    commitment,
+                    UnconfirmedReason::ConnectionClosed,
+                    &mut state.result.unconfirmed,
+                );
+            }
+        } else {
+            tracing::info!(
+                "WebSocket {} finished monitoring all signatures.",
+                self.ws_url
+            );
+        }
+
+        Ok(state.result)
     }
 
-    pub async fn monitor_confirmation(&self) -> Result<Vec<ConfirmationResult>> {
-        let (mut ws_stream, _) = connect_async(&self.ws_url).await?;
+    /// Re-establish the connection with exponential backoff. Returns `None` once the attempts
+    /// are exhausted or nothing is pending any more.
+    async fn reconnect(&self, state: &mut MonitorState) -> Option<WsStream> {
+        let policy = &self.settings.reconnect;
+        let mut backoff = Duration::from_millis(policy.initial_backoff_ms);
 
-        let mut request_id_counter: u64 = 1;
-        let mut pending_acknowledgements: HashMap<u64, Signature> = HashMap::new();
-        let mut active_subscriptions: HashMap<u64, Signature> = HashMap::new();
-        let mut pending_notifications: HashSet<Signature> =
-            self.signatures.iter().cloned().collect();
+        for attempt in 1..=policy.max_attempts {
+            tracing::info!(
+                "Reconnecting WebSocket {} in {:?} (attempt {}/{}), {} signatures pending",
+                self.ws_url,
+                backoff,
+                attempt,
+                policy.max_attempts,
+                state.pending_notifications.len()
+            );
+            tokio::time::sleep(backoff).await;
+
+            // Deadlines keep running while disconnected.
+            self.expire_pending(state);
+            if state.pending_notifications.is_empty() {
+                return None;
+            }
+
+            match self.connect().await {
+                Ok(stream) => return Some(stream),
+                Err(e) => {
+                    tracing::warn!(
+                        "Reconnect attempt {} to {} failed: {}",
+                        attempt,
+                        self.ws_url,
+                        e
+                    );
+                }
+            }
+            backoff = (backoff * 2).min(Duration::from_millis(policy.max_backoff_ms));
+        }
 
-        let mut confirmations: Vec<ConfirmationResult> = Vec::new();
+        tracing::error!(
+            "Giving up on WebSocket {} after {} reconnect attempts",
+            self.ws_url,
+            policy.max_attempts
+        );
+        None
+    }
 
-        for signature_to_subscribe in &self.signatures {
-            let current_request_id = request_id_counter;
-            request_id_counter += 1;
+    /// Subscribe every pending (signature, commitment) on `ws_stream` and process messages until
+    /// nothing is pending or the connection is lost.
+    async fn run_connection(
+        &self,
+        mut ws_stream: WsStream,
+        resubscribed: bool,
+        state: &mut MonitorState,
+    ) -> Result<ConnectionEnd> {
+        let mut pending_acknowledgements: HashMap<u64, (Signature, Commitment)> = HashMap::new();
+        let mut active_subscriptions: HashMap<u64, (Signature, Commitment)> = HashMap::new();
+        let subscription_targets: Vec<(Signature, Commitment)> =
+            state.pending_notifications.iter().cloned().collect();
 
+        for (current_request_id, (signature_to_subscribe, commitment)) in
+            (1_u64..).zip(subscription_targets.iter())
+        {
             let subscription_payload = SignatureSubscription {
                 jsonrpc: "2.0".to_string(),
                 id: current_request_id, // Use unique id for each subscription request
@@ -93,29 +466,46 @@ impl WebSocketHandle {
                 params: vec![
                     serde_json::to_value(signature_to_subscribe.to_string())?,
                     serde_json::json!({
-                        // NOTE: "processed" commitment because we aim to compare the performance of different RPC nodes
-                        "commitment": "processed",
+                        "commitment": commitment.as_str(),
                     }),
                 ],
             };
 
             let payload_str = serde_json::to_string(&subscription_payload)
                 .expect("Failed to serialize subscription payload");
-            ws_stream
-                .send(Message::Text(payload_str))
-                .await
-                .expect("Failed to send subscription request");
-            pending_acknowledgements.insert(current_request_id, *signature_to_subscribe);
+            if let Err(e) = ws_stream.send(Message::Text(payload_str)).await {
+                tracing::error!(
+                    "Failed to send subscription request to {}: {}",
+                    self.ws_url,
+                    e
+                );
+                return Ok(ConnectionEnd::Lost(format!(
+                    "failed to send subscription request: {}",
+                    e
+                )));
+            }
+            pending_acknowledgements
+                .insert(current_request_id, (*signature_to_subscribe, *commitment));
         }
 
         tracing::info!(
-            "All subscription requests sent for {} signatures to {}. Waiting for acknowledgements and notifications.",
+            "All subscription requests sent for {} (signature, commitment) pairs to {}. Waiting for acknowledgements and notifications.",
             pending_acknowledgements.len(),
             self.ws_url
         );
 
-        while !pending_notifications.is_empty() {
-            match ws_stream.next().await {
+        let mut deadline_check = tokio::time::interval(DEADLINE_CHECK_INTERVAL);
+
+        while !state.pending_notifications.is_empty() {
+            let next_message = tokio::select! {
+                message = ws_stream.next() => message,
+                _ = deadline_check.tick() => {
+                    self.expire_pending(state);
+                    continue;
+                }
+            };
+
+            match next_message {
                 Some(Ok(msg)) => match msg {
                     Message::Text(text) => {
                         tracing::debug!("Received WebSocket message on {}: {}", self.ws_url, text);
@@ -139,14 +529,21 @@ impl WebSocketHandle {
                         {
                             match serde_json::from_value::<SubscriptionAcknowledgement>(v.clone()) {
                                 Ok(ack) => {
-                                    if let Some(signature) =
+                                    if let Some((signature, commitment)) =
                                         pending_acknowledgements.remove(&ack.id)
                                     {
                                         tracing::info!(
-                                            "Subscription acknowledged for signature {} (Request ID: {}). WebSocket Subscription ID: {}. URL: {}",
-                                            signature, ack.id, ack.result, self.ws_url
+                                            "Subscription acknowledged for signature {} at {} (Request ID: {}). WebSocket Subscription ID: {}. URL: {}",
+                                            signature, commitment, ack.id, ack.result, self.ws_url
                                         );
-                                        active_subscriptions.insert(ack.result, signature);
+                                        self.event_log.record(Event::SubscriptionAcknowledged {
+                                            signature: signature.to_string(),
+                                            node: self.node_name.clone(),
+                                            commitment,
+                                            subscription_id: ack.result,
+                                        });
+                                        active_subscriptions
+                                            .insert(ack.result, (signature, commitment));
                                     } else {
                                         tracing::warn!(
                                             "Received acknowledgement for unknown request ID: {}. URL: {}. Raw: {}",
@@ -169,43 +566,58 @@ impl WebSocketHandle {
                         {
                             match serde_json::from_value::<SignatureNotification>(v) {
                                 Ok(notification) => {
-                                    if let Some(signature) =
+                                    if let Some(&(signature, commitment)) =
                                         active_subscriptions.get(&notification.params.subscription)
                                     {
+                                        if !state
+                                            .pending_notifications
+                                            .contains(&(signature, commitment))
+                                        {
+                                            // Already given up on (timed out); ignore late arrivals.
+                                            continue;
+                                        }
                                         let result_data = notification.params.result;
-                                        let no_error = result_data
-                                            .value
-                                            .err
-                                            .as_ref()
-                                            .is_none_or(|e_val| e_val.is_null());
+                                        let err = describe_transaction_error(
+                                            result_data.value.err.as_ref(),
+                                        );
                                         let slot = result_data.context.slot;
-                                        let confirmation_timestamp = SystemTime::now()
-                                            .duration_since(std::time::UNIX_EPOCH)
-                                            .expect("Time went backwards")
-                                            .as_micros()
-                                            as u64;
+                                        let confirmation_timestamp = clock::now_us();
+                                        self.event_log.record_at(
+                                            confirmation_timestamp,
+                                            Event::NotificationReceived {
+                                                signature: signature.to_string(),
+                                                node: self.node_name.clone(),
+                                                commitment,
+                                                slot,
+                                                err: result_data.value.err.clone(),
+                                                resubscribed,
+                                            },
+                                        );
+                                        self.send_tracker.record_notified(signature);
 
-                                        if no_error {
+                                        if err.is_none() {
                                             tracing::info!(
-                                                "Signature {} confirmed (finalized) at slot {} on {}. Timestamp (us): {}. WebSocket Sub ID: {}",
-                                                signature, slot, self.ws_url, confirmation_timestamp, notification.params.subscription
+                                                "Signature {} reached {} at slot {} on {}. Timestamp (us): {}. WebSocket Sub ID: {}",
+                                                signature, commitment, slot, self.ws_url, confirmation_timestamp, notification.params.subscription
                                             );
-                                            confirmations.push(ConfirmationResult {
-                                                signature: signature.to_string(),
-                                                timestamp_us: confirmation_timestamp,
-                                            });
                                         } else {
                                             tracing::error!(
-                                                "Signature {} finalized with error on {}: {:?}. Slot: {}. Timestamp (us): {}. WebSocket Sub ID: {}. Raw: {}",
-                                                signature, self.ws_url, result_data.value.err, slot, confirmation_timestamp, notification.params.subscription, text
+                                                "Signature {} reached {} with error on {}: {}. Slot: {}. Timestamp (us): {}. WebSocket Sub ID: {}. Raw: {}",
+                                                signature, commitment, self.ws_url, err.as_deref().unwrap_or_default(), slot, confirmation_timestamp, notification.params.subscription, text
                                             );
-                                            confirmations.push(ConfirmationResult {
-                                                signature: signature.to_string(),
-                                                timestamp_us: confirmation_timestamp,
-                                            });
                                         }
+                                        state.result.confirmations.push(ConfirmationResult {
+                                            signature: signature.to_string(),
+                                            commitment,
+                                            timestamp_us: confirmation_timestamp,
+                                            resubscribed,
+                                            slot,
+                                            err,
+                                        });
                                         // Remove from pending_notifications regardless of error, as we've received its terminal state.
-                                        pending_notifications.remove(signature);
+                                        state
+                                            .pending_notifications
+                                            .remove(&(signature, commitment));
                                     } else {
                                         tracing::warn!(
                                             "Received notification for unknown/inactive subscription ID: {}. URL: {}. Raw: {}",
@@ -234,7 +646,10 @@ impl WebSocketHandle {
                             self.ws_url,
                             close_frame
                         );
-                        break; // Exit loop on close
+                        return Ok(ConnectionEnd::Lost(format!(
+                            "closed by server: {:?}",
+                            close_frame
+                        )));
                     }
                     _ => {
                         tracing::debug!("Received non-text WebSocket message on {}", self.ws_url);
@@ -245,35 +660,21 @@ impl WebSocketHandle {
                         "Error reading from WebSocket stream {}: {}. Remaining signatures: {}",
                         self.ws_url,
                         e,
-                        pending_notifications.len()
+                        state.pending_notifications.len()
                     );
-                    break; // Connection error, stop monitoring this WebSocket
+                    return Ok(ConnectionEnd::Lost(format!("stream error: {}", e)));
                 }
                 None => {
                     tracing::info!(
                         "WebSocket stream {} ended. Remaining signatures: {}",
                         self.ws_url,
-                        pending_notifications.len()
+                        state.pending_notifications.len()
                     );
-                    break; // Stream ended
+                    return Ok(ConnectionEnd::Lost("stream ended".to_string()));
                 }
             }
         }
 
-        if !pending_notifications.is_empty() {
-            tracing::warn!(
-                "WebSocket {} finished monitoring with {} pending signatures: {:?}",
-                self.ws_url,
-                pending_notifications.len(),
-                pending_notifications
-            );
-        } else {
-            tracing::info!(
-                "WebSocket {} finished monitoring all signatures.",
-                self.ws_url
-            );
-        }
-
-        Ok(confirmations)
+        Ok(ConnectionEnd::Completed)
     }
 }